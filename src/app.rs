@@ -0,0 +1,981 @@
+use std::{cell::Cell, collections::HashMap, fs::File, io::Read, path::{Path, PathBuf}};
+
+use globset::Glob;
+use ratatui::{style::Style, text::Line};
+
+use crate::{
+    bundle::{self, Parts}, cli::{Options, OutputMode}, content::{self, Kind}, fuzzy, git, highlight::{self, Highlighter, Palette}, lang, listing::{Entry, Filter, Part},
+    outline::{self, Symbol}, presets::{self, Preset}, redact::Redactor, secrets, state, tokens::Tokenizer, Sink,
+};
+
+/// Bytes read from disk each time the preview needs more of a file.
+const PREVIEW_CHUNK: usize = 64 * 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Focus {
+    List,
+    Preview,
+}
+
+/// The file behind the preview, read incrementally as the user scrolls.
+struct PreviewSource {
+    file: File,
+    kind: Kind,
+    /// Bytes consumed so far, for hex dump offsets.
+    offset: u64,
+    /// Bytes after the last newline of the previous chunk.
+    pending: Vec<u8>,
+    highlighter: Highlighter,
+    done: bool,
+}
+
+/// A modal interaction that takes over the keyboard until it's finished.
+pub enum Prompt {
+    /// Typing a name to save the current selection under.
+    SavePreset(String),
+    /// Choosing a saved preset to load.
+    LoadPreset { presets: Vec<Preset>, cursor: usize },
+    /// Emitting was asked for, but some selected files look like they hold
+    /// credentials; lists each with its reasons.
+    Secrets { mode: OutputMode, sink: Sink, files: Vec<(String, Vec<String>)> },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mark {
+    None,
+    Partial,
+    All,
+    /// A selected file going out as declarations only.
+    Skeleton,
+}
+
+pub struct App {
+    pub items: Vec<Entry>,
+    /// Indices into `items` of the rows currently shown (ancestors expanded).
+    pub visible: Vec<usize>,
+    /// Position within `visible`.
+    pub cursor: usize,
+    pub preview_content: String,
+    /// `preview_content` highlighted and numbered, or empty when the preview
+    /// isn't file contents (directories, errors).
+    pub preview_lines: Vec<Line<'static>>,
+    pub palette: Palette,
+    preview_source: Option<PreviewSource>,
+    /// First preview line on screen.
+    pub preview_scroll: usize,
+    /// Line the cursor is on while the preview has focus.
+    pub preview_cursor: usize,
+    /// Characters of each file line scrolled off the left of the preview.
+    pub preview_column: usize,
+    /// Where visual mode started, while picking a line range.
+    pub visual_anchor: Option<usize>,
+    /// Whether the preview shows the file's symbols instead of its text.
+    pub outline_view: bool,
+    /// The previewed file's symbols, when in outline view and it has any.
+    pub outline: Option<Vec<Symbol>>,
+    pub outline_cursor: usize,
+    /// Rows available to the preview, recorded by the last draw.
+    pub preview_height: Cell<usize>,
+    /// Rows available to the file list, recorded by the last draw.
+    pub list_height: Cell<usize>,
+    pub focus: Focus,
+    pub show_preview: bool,
+    /// Fuzzy filter over relative paths; while non-empty the tree is
+    /// flattened to the matching files, best match first.
+    pub query: String,
+    /// Whether keystrokes currently go to the filter bar.
+    pub filtering: bool,
+    /// Matched char positions in each visible row's relative path.
+    pub highlights: HashMap<usize, Vec<usize>>,
+    pub tokenizer: Tokenizer,
+    pub budget: Option<usize>,
+    /// Whether bundles start with a tree of the project.
+    pub include_tree: bool,
+    pub root: PathBuf,
+    pub prompt: Option<Prompt>,
+    /// One-line feedback for the last action, shown under the list.
+    pub status: Option<String>,
+    /// Masks spans of emitted text; `redactor.secrets` is set once the user
+    /// chooses to redact whatever the secret scanner finds.
+    pub redactor: Redactor,
+    /// Emit without checking for secrets first.
+    pub allow_secrets: bool,
+}
+
+impl App {
+    pub fn new(mut items: Vec<Entry>, opts: &Options, filter: &Filter, redactor: Redactor) -> Self {
+        for it in &mut items { it.skeleton = filter.wants_skeleton(it); }
+        let mut app = Self {
+            items,
+            visible: Vec::new(),
+            cursor: 0,
+            preview_content: String::new(),
+            preview_lines: Vec::new(),
+            palette: Palette::detect(),
+            preview_source: None,
+            preview_scroll: 0,
+            preview_cursor: 0,
+            preview_column: 0,
+            visual_anchor: None,
+            outline_view: false,
+            outline: None,
+            outline_cursor: 0,
+            preview_height: Cell::new(20),
+            list_height: Cell::new(50),
+            focus: Focus::List,
+            show_preview: true,
+            query: String::new(),
+            filtering: false,
+            highlights: HashMap::new(),
+            tokenizer: opts.tokenizer,
+            budget: opts.budget,
+            include_tree: opts.tree,
+            root: opts.root.clone(),
+            prompt: None,
+            status: None,
+            redactor,
+            allow_secrets: opts.allow_secrets,
+        };
+        if filter.preselects() { app.select_matching(filter); }
+        app.refresh_visible();
+        app.update_preview();
+        app
+    }
+    pub fn refresh_visible(&mut self) {
+        let current = self.current_index();
+        self.visible.clear();
+        self.highlights.clear();
+        if self.query.is_empty() {
+            let mut i = 0;
+            while i < self.items.len() {
+                self.visible.push(i);
+                i = if self.items[i].is_dir && !self.items[i].expanded { self.subtree_end(i) } else { i + 1 };
+            }
+        } else {
+            let mut scored: Vec<(i64, usize)> = Vec::new();
+            for (i, e) in self.items.iter().enumerate().filter(|(_, e)| !e.is_dir) {
+                if let Some((score, positions)) = fuzzy::score(&self.query, &e.rel.to_string_lossy()) {
+                    scored.push((score, i));
+                    self.highlights.insert(i, positions);
+                }
+            }
+            scored.sort_by_key(|&(score, i)| (std::cmp::Reverse(score), i));
+            self.visible.extend(scored.into_iter().map(|(_, i)| i));
+        }
+        self.cursor = current
+            .and_then(|c| self.visible.iter().position(|&v| v == c))
+            .unwrap_or_else(|| self.cursor.min(self.visible.len().saturating_sub(1)));
+    }
+    /// One past the last descendant of `idx`.
+    pub fn subtree_end(&self, idx: usize) -> usize {
+        let depth = self.items[idx].depth;
+        self.items[idx + 1..].iter().position(|e| e.depth <= depth).map_or(self.items.len(), |p| idx + 1 + p)
+    }
+    pub fn current_index(&self) -> Option<usize> {
+        self.visible.get(self.cursor).copied()
+    }
+    pub fn mark(&self, idx: usize) -> Mark {
+        if !self.items[idx].is_dir {
+            let e = &self.items[idx];
+            return match (e.selected, e.part()) {
+                (false, _) => Mark::None,
+                (true, None) => Mark::All,
+                (true, Some(Part::Ranges(_))) => Mark::Partial,
+                (true, Some(Part::Skeleton)) => Mark::Skeleton,
+            };
+        }
+        let files = self.items[idx + 1..self.subtree_end(idx)].iter().filter(|e| !e.is_dir);
+        let (total, selected) = files.fold((0, 0), |(t, s), e| (t + 1, s + e.selected as usize));
+        match selected {
+            0 => Mark::None,
+            s if s == total => Mark::All,
+            _ => Mark::Partial,
+        }
+    }
+    pub fn set_subtree(&mut self, idx: usize, selected: bool) {
+        let end = self.subtree_end(idx);
+        for it in &mut self.items[idx..end] {
+            if !it.is_dir { it.set_selected(selected); }
+        }
+    }
+    /// Rows that bulk selection applies to: everything, or only the
+    /// matches while a filter is active.
+    fn bulk_targets(&self) -> Vec<usize> {
+        if self.query.is_empty() { (0..self.items.len()).collect() } else { self.visible.clone() }
+    }
+    /// Adds every targeted file that satisfies `pick` to the selection.
+    fn select_where(&mut self, pick: impl Fn(&Entry) -> bool) {
+        for i in self.bulk_targets() {
+            let it = &mut self.items[i];
+            if !it.is_dir && pick(it) { it.selected = true; }
+        }
+    }
+    /// Everything except hidden and gitignored files.
+    pub fn select_all(&mut self) {
+        self.select_where(|e| !e.hidden && !e.ignored);
+    }
+    /// Everything that isn't gitignored, dotfiles included.
+    pub fn select_all_with_hidden(&mut self) {
+        self.select_where(|e| !e.ignored);
+    }
+    /// Replaces the selection with just the gitignored files.
+    pub fn select_only_ignored(&mut self) {
+        self.select_none();
+        self.select_where(|e| e.ignored);
+    }
+    /// Adds files with the given kinds of git changes to the selection.
+    pub fn select_changed(&mut self, pick: git::Pick) {
+        self.select_where(|e| e.git.is_some_and(|c| pick.matches(&c)));
+    }
+    pub fn invert_selection(&mut self) {
+        for i in self.bulk_targets() {
+            let it = &mut self.items[i];
+            if !it.is_dir { it.set_selected(!it.selected); }
+        }
+    }
+    pub fn select_matching(&mut self, filter: &Filter) {
+        for it in &mut self.items { it.set_selected(filter.wants(it)); }
+    }
+    pub fn select_none(&mut self) {
+        for i in self.bulk_targets() { self.items[i].set_selected(false); }
+    }
+    pub fn start_filter(&mut self) {
+        self.filtering = true;
+    }
+    pub fn finish_filter(&mut self) {
+        self.filtering = false;
+    }
+    pub fn push_query(&mut self, c: char) {
+        self.query.push(c);
+        self.cursor = 0;
+        self.refresh_filter();
+    }
+    pub fn pop_query(&mut self) {
+        self.query.pop();
+        self.refresh_filter();
+    }
+    pub fn clear_filter(&mut self) {
+        self.query.clear();
+        self.filtering = false;
+        self.refresh_filter();
+    }
+    fn refresh_filter(&mut self) {
+        self.refresh_visible();
+        self.update_preview();
+    }
+    pub fn select_only_n(&mut self, n: usize) {
+        self.select_none();
+        if !self.visible.is_empty() {
+            self.cursor = n.min(self.visible.len() - 1);
+            self.set_subtree(self.visible[self.cursor], true);
+            self.update_preview();
+        }
+    }
+    pub fn toggle_current(&mut self) {
+        let Some(idx) = self.current_index() else { return };
+        // A file picked in part or as a skeleton is still selected, so space
+        // drops it rather than widening it to the whole file.
+        let select = if self.items[idx].is_dir { self.mark(idx) != Mark::All } else { !self.items[idx].selected };
+        self.set_subtree(idx, select);
+    }
+    pub fn expand_current(&mut self) {
+        // The filtered view is flat, there is no tree to fold.
+        if !self.query.is_empty() { return; }
+        let Some(idx) = self.current_index() else { return };
+        if !self.items[idx].is_dir { return; }
+        if self.items[idx].expanded {
+            if self.subtree_end(idx) > idx + 1 { self.move_down(); }
+        } else {
+            self.items[idx].expanded = true;
+            self.refresh_visible();
+        }
+    }
+    pub fn collapse_current(&mut self) {
+        if !self.query.is_empty() { return; }
+        let Some(idx) = self.current_index() else { return };
+        if self.items[idx].is_dir && self.items[idx].expanded {
+            self.items[idx].expanded = false;
+            self.refresh_visible();
+        } else if let Some(parent) = self.items[idx].parent {
+            self.items[parent].expanded = false;
+            self.refresh_visible();
+            if let Some(pos) = self.visible.iter().position(|&v| v == parent) { self.cursor = pos; }
+            self.update_preview();
+        }
+    }
+    pub fn move_up(&mut self) {
+        if self.visible.is_empty() { return; }
+        if self.cursor == 0 { self.cursor = self.visible.len() - 1; } else { self.cursor -= 1; }
+        self.update_preview();
+    }
+    pub fn move_down(&mut self) {
+        if self.visible.is_empty() { return; }
+        self.cursor = (self.cursor + 1) % self.visible.len();
+        self.update_preview();
+    }
+    pub fn selected_paths(&self) -> Vec<PathBuf> {
+        self.items.iter().filter(|e| e.selected && !e.is_dir).map(|e| e.path.clone()).collect()
+    }
+    pub fn selected_entries(&self) -> Vec<&Entry> {
+        self.items.iter().filter(|e| e.selected && !e.is_dir).collect()
+    }
+    /// The selected files that go out only in part, and which part.
+    pub fn selected_parts(&self) -> Parts {
+        self.selected_entries().iter().filter_map(|e| Some((e.path.clone(), e.part()?))).collect()
+    }
+    pub fn selected_count(&self) -> usize {
+        self.items.iter().filter(|e| e.selected && !e.is_dir).count()
+    }
+    /// Counts tokens for every file that is selected or near the cursor and
+    /// hasn't been counted yet. Cheap to call once per frame.
+    pub fn count_tokens(&mut self) {
+        let tokenizer = self.tokenizer;
+        for i in self.rows_in_reach() {
+            let e = &mut self.items[i];
+            if e.is_dir || e.tokens.is_some() { continue; }
+            e.tokens = Some(match e.part() {
+                None => tokenizer.count_file(&e.path),
+                part => tokenizer.count(&content::read_text(&e.path).map(|t| {
+                    let lang = lang::detect_with_shebang(&e.path, &t);
+                    bundle::part_text(lang, t, part.as_ref())
+                }).unwrap_or_default()),
+            });
+        }
+    }
+    /// Checks rows that are selected or near the cursor for secrets, so the
+    /// list can flag them.
+    pub fn scan_secrets(&mut self) {
+        for i in self.rows_in_reach() {
+            let e = &mut self.items[i];
+            if !e.is_dir && e.secrets.is_none() {
+                e.secrets = Some(secrets::check_file(&e.path, Some(secrets::BROWSE_SCAN_LIMIT)).len());
+            }
+        }
+    }
+    /// Selected files plus the visible rows within a screen of the cursor;
+    /// the ones worth measuring without reading the whole tree.
+    fn rows_in_reach(&self) -> Vec<usize> {
+        let reach = self.list_height.get();
+        let near = self.cursor.saturating_sub(reach)..(self.cursor + reach).min(self.visible.len());
+        self.visible[near].iter().copied()
+            .chain((0..self.items.len()).filter(|&i| self.items[i].selected))
+            .collect()
+    }
+    /// Every selected file the secret scanner has concerns about, with the
+    /// concerns. Scans the whole of each file, however big.
+    pub fn flagged_selection(&self) -> Vec<(String, Vec<String>)> {
+        self.selected_entries().iter()
+            .map(|e| (e.rel.to_string_lossy().into_owned(), secrets::check_file(&e.path, None)))
+            .filter(|(_, reasons)| !reasons.is_empty())
+            .collect()
+    }
+    /// Returns `(mode, sink)` if output can go ahead; otherwise opens the
+    /// secrets prompt so the user can decide.
+    pub fn request_emit(&mut self, mode: OutputMode, sink: Sink, json_contents: bool) -> Option<(OutputMode, Sink)> {
+        if self.allow_secrets || self.redactor.secrets || !mode.carries_contents(json_contents) {
+            return Some((mode, sink));
+        }
+        let files = self.flagged_selection();
+        if files.is_empty() { return Some((mode, sink)); }
+        self.prompt = Some(Prompt::Secrets { mode, sink, files });
+        None
+    }
+    pub fn selected_tokens(&self) -> usize {
+        self.items.iter().filter(|e| e.selected && !e.is_dir).filter_map(|e| e.tokens).sum()
+    }
+    pub fn over_budget(&self) -> bool {
+        self.budget.is_some_and(|b| self.selected_tokens() > b)
+    }
+    
+    pub fn update_preview(&mut self) {
+        self.preview_lines.clear();
+        self.preview_source = None;
+        self.preview_scroll = 0;
+        self.preview_cursor = 0;
+        self.preview_column = 0;
+        self.visual_anchor = None;
+        self.outline = None;
+        self.outline_cursor = 0;
+        let Some(idx) = self.current_index() else {
+            self.preview_content = "No files available".to_string();
+            return;
+        };
+
+        let entry = &self.items[idx];
+        if entry.is_dir {
+            let children: Vec<String> = self.items[idx + 1..self.subtree_end(idx)].iter()
+                .filter(|e| e.parent == Some(idx))
+                .map(|e| if e.is_dir { format!("{}/", e.name) } else { e.name.clone() })
+                .collect();
+            self.preview_content = if children.is_empty() { "<empty directory>".to_string() } else { children.join("\n") };
+            return;
+        }
+        let path = entry.path.clone();
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                self.preview_content = format!("Error reading file: {}", e);
+                return;
+            }
+        };
+        let size = file.metadata().map_or(0, |m| m.len());
+        if size == 0 {
+            self.preview_content = "<empty file>".to_string();
+            return;
+        }
+        let kind = content::sniff_file(&path).unwrap_or(Kind::Binary);
+        self.preview_content.clear();
+        self.preview_source = Some(PreviewSource { file, kind, offset: 0, pending: Vec::new(), highlighter: Highlighter::new(None, &self.palette), done: false });
+        if kind == Kind::Binary {
+            self.ensure_preview_lines(0);
+            return;
+        }
+        while self.preview_content.is_empty() && self.preview_source.as_ref().is_some_and(|s| !s.done) {
+            self.load_preview_chunk();
+        }
+        // The language can only be sniffed once the first line is in.
+        let lang = lang::detect_with_shebang(&path, &self.preview_content);
+        if let Some(source) = &mut self.preview_source {
+            source.highlighter = Highlighter::new(lang, &self.palette);
+            self.preview_lines = source.highlighter.lines(&self.preview_content);
+            mark_redactions(&self.redactor, self.palette.redacted(), &self.preview_content, &mut self.preview_lines);
+        }
+        self.ensure_preview_lines(0);
+        if self.outline_view { self.load_outline(); }
+    }
+
+    /// Reads the next chunk of the previewed file, keeping only whole lines.
+    fn load_preview_chunk(&mut self) {
+        let Some(source) = &mut self.preview_source else { return };
+        if source.done { return; }
+        let mut buf = vec![0; PREVIEW_CHUNK];
+        let n = match source.file.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) => {
+                self.preview_content = format!("Error reading file: {}", e);
+                self.preview_lines.clear();
+                self.preview_source = None;
+                return;
+            }
+        };
+        if source.kind == Kind::Binary {
+            let lines = content::hex_dump(&buf[..n], source.offset);
+            self.preview_lines.extend(lines.into_iter().map(Line::raw));
+            source.offset += n as u64;
+            source.done = n == 0;
+            return;
+        }
+        source.pending.extend_from_slice(&buf[..n]);
+        let cut = if n == 0 {
+            source.done = true;
+            source.pending.len()
+        } else {
+            match source.pending.iter().rposition(|&b| b == b'\n') {
+                Some(pos) => pos + 1,
+                None => return,
+            }
+        };
+        let chunk: Vec<u8> = source.pending.drain(..cut).collect();
+        let text = match String::from_utf8(chunk) {
+            Ok(text) => text,
+            Err(e) => {
+                source.kind = Kind::LossyText;
+                String::from_utf8_lossy(e.as_bytes()).into_owned()
+            }
+        };
+        let mut lines = source.highlighter.lines(&text);
+        mark_redactions(&self.redactor, self.palette.redacted(), &text, &mut lines);
+        self.preview_lines.extend(lines);
+        self.preview_content.push_str(&text);
+    }
+
+    /// Loads until line `line` plus a screenful is available, or the file ends.
+    fn ensure_preview_lines(&mut self, line: usize) {
+        let want = line + self.preview_height.get();
+        while self.preview_lines.len() < want && self.preview_source.as_ref().is_some_and(|s| !s.done) {
+            self.load_preview_chunk();
+        }
+    }
+
+    pub fn preview_kind(&self) -> Option<Kind> {
+        self.preview_source.as_ref().map(|s| s.kind)
+    }
+
+    pub fn preview_fully_loaded(&self) -> bool {
+        self.preview_source.as_ref().is_none_or(|s| s.done)
+    }
+
+    pub fn scroll_preview(&mut self, delta: isize) {
+        let target = self.preview_scroll.saturating_add_signed(delta);
+        self.ensure_preview_lines(target);
+        self.preview_scroll = target.min(self.preview_lines.len().saturating_sub(1));
+    }
+
+    /// Pages the preview; with the preview focused the cursor moves along.
+    pub fn page_preview(&mut self, pages: isize) {
+        let page = self.preview_height.get().saturating_sub(1).max(1) as isize;
+        if self.focus == Focus::Preview { self.move_preview_cursor(page * pages); } else { self.scroll_preview(page * pages); }
+    }
+
+    pub fn preview_top(&mut self) {
+        self.preview_scroll = 0;
+        self.preview_cursor = 0;
+    }
+
+    pub fn preview_bottom(&mut self) {
+        self.ensure_preview_lines(usize::MAX / 2);
+        self.preview_scroll = self.preview_lines.len().saturating_sub(self.preview_height.get());
+        self.preview_cursor = self.preview_lines.len().saturating_sub(1);
+    }
+
+    /// Scrolls the preview sideways, no further than the last character of
+    /// the widest line loaded so far.
+    pub fn scroll_preview_sideways(&mut self, delta: isize) {
+        let widest = self.preview_lines.iter()
+            .map(|l| l.spans.iter().skip(1).map(|s| s.content.chars().count()).sum::<usize>())
+            .max()
+            .unwrap_or(0);
+        self.preview_column = self.preview_column.saturating_add_signed(delta).min(widest.saturating_sub(1));
+    }
+
+    /// Moves the preview cursor, scrolling to keep it on screen.
+    pub fn move_preview_cursor(&mut self, delta: isize) {
+        let target = self.preview_cursor.saturating_add_signed(delta);
+        self.ensure_preview_lines(target);
+        self.preview_cursor = target.min(self.preview_lines.len().saturating_sub(1));
+        let height = self.preview_height.get().max(1);
+        if self.preview_cursor < self.preview_scroll {
+            self.preview_scroll = self.preview_cursor;
+        } else if self.preview_cursor >= self.preview_scroll + height {
+            self.preview_scroll = self.preview_cursor + 1 - height;
+        }
+    }
+
+    /// Line ranges only make sense for text; hex dumps and directory
+    /// listings have no lines to pick.
+    fn preview_has_lines(&self) -> bool {
+        matches!(self.preview_kind(), Some(Kind::Text | Kind::LossyText)) && !self.preview_lines.is_empty()
+    }
+
+    pub fn toggle_visual(&mut self) {
+        if !self.preview_has_lines() || self.outline_view { return; }
+        self.visual_anchor = match self.visual_anchor {
+            Some(_) => None,
+            None => Some(self.preview_cursor),
+        };
+    }
+
+    /// The lines (0-based, inclusive) visual mode covers right now.
+    pub fn visual_span(&self) -> Option<(usize, usize)> {
+        self.visual_anchor.map(|a| (a.min(self.preview_cursor), a.max(self.preview_cursor)))
+    }
+
+    /// Adds the visual span (or the cursor line) to the previewed file's
+    /// ranges and selects the file.
+    pub fn add_preview_range(&mut self) {
+        if !self.preview_has_lines() { return; }
+        let Some(idx) = self.current_index() else { return };
+        let (start, end) = self.visual_span().unwrap_or((self.preview_cursor, self.preview_cursor));
+        self.visual_anchor = None;
+        let e = &mut self.items[idx];
+        e.ranges = merge_range(&e.ranges, (start + 1, end + 1));
+        e.selected = true;
+        e.skeleton = false;
+        e.tokens = None;
+        self.status = Some(ranges_status(e));
+    }
+
+    pub fn toggle_outline(&mut self) {
+        self.outline_view = !self.outline_view;
+        self.visual_anchor = None;
+        if self.outline_view { self.load_outline(); } else { self.outline = None; }
+    }
+
+    /// Parses the previewed file's outline, if its language has one.
+    fn load_outline(&mut self) {
+        self.outline = None;
+        self.outline_cursor = 0;
+        if !self.preview_has_lines() { return; }
+        let Some(idx) = self.current_index() else { return };
+        let path = &self.items[idx].path;
+        let Ok(text) = content::read_text(path) else { return };
+        let Some(lang) = lang::detect_with_shebang(path, &text).filter(|l| outline::supports(l)) else { return };
+        self.outline = Some(outline::outline(lang, &text));
+    }
+
+    pub fn move_outline_cursor(&mut self, delta: isize) {
+        let len = self.outline.as_ref().map_or(0, Vec::len);
+        self.outline_cursor = self.outline_cursor.saturating_add_signed(delta).min(len.saturating_sub(1));
+    }
+
+    /// Whether the previewed file's ranges cover all of `symbol`.
+    pub fn symbol_picked(&self, symbol: &Symbol) -> bool {
+        self.current_index().is_some_and(|i| self.items[i].ranges.iter().any(|&(s, e)| s <= symbol.start && symbol.end <= e))
+    }
+
+    /// Adds the symbol under the outline cursor to the file's ranges, or
+    /// takes it out again. Picking a symbol selects the file; taking out the
+    /// last one deselects it.
+    pub fn toggle_symbol(&mut self) {
+        let Some(symbol) = self.outline.as_ref().and_then(|o| o.get(self.outline_cursor)) else { return };
+        let Some(idx) = self.current_index() else { return };
+        let (range, picked) = ((symbol.start, symbol.end), self.symbol_picked(symbol));
+        let e = &mut self.items[idx];
+        if picked {
+            e.ranges = remove_range(&e.ranges, range);
+            if e.ranges.is_empty() { e.selected = false; }
+        } else {
+            e.ranges = merge_range(&e.ranges, range);
+            e.selected = true;
+            e.skeleton = false;
+        }
+        e.tokens = None;
+        self.status = Some(if e.selected { ranges_status(e) } else { format!("{}: deselected", e.rel.display()) });
+    }
+
+    /// Switches the file under the cursor between whole and skeleton, or on
+    /// a directory, every selected file under it that can have one.
+    pub fn toggle_skeleton(&mut self) {
+        let Some(idx) = self.current_index() else { return };
+        let rel = self.items[idx].rel.display().to_string();
+        let targets: Vec<usize> = if self.items[idx].is_dir {
+            (idx + 1..self.subtree_end(idx)).filter(|&i| !self.items[i].is_dir && self.items[i].selected && outline::supports_file(&self.items[i].path)).collect()
+        } else if outline::supports_file(&self.items[idx].path) {
+            vec![idx]
+        } else {
+            self.status = Some(format!("no skeleton for {}: only Rust, Python, JS/TS, Go and C/C++", rel));
+            return;
+        };
+        if targets.is_empty() {
+            self.status = Some(format!("no selected files under {} can be a skeleton", rel));
+            return;
+        }
+        let on = !targets.iter().all(|&i| self.items[i].skeleton && self.items[i].ranges.is_empty());
+        for &i in &targets {
+            let e = &mut self.items[i];
+            e.skeleton = on;
+            e.tokens = None;
+            if on {
+                e.selected = true;
+                e.ranges.clear();
+            }
+        }
+        let what = if targets.len() == 1 { self.items[targets[0]].rel.display().to_string() } else { format!("{} files", targets.len()) };
+        self.status = Some(format!("{}: {}", what, if on { "skeleton" } else { "whole" }));
+    }
+
+    /// Goes back to emitting the whole previewed file (or its skeleton).
+    pub fn clear_preview_ranges(&mut self) {
+        self.visual_anchor = None;
+        let Some(idx) = self.current_index() else { return };
+        let e = &mut self.items[idx];
+        if e.ranges.is_empty() { return; }
+        e.ranges.clear();
+        e.tokens = None;
+        self.status = Some(format!("{}: whole file", e.rel.display()));
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::List if self.show_preview => Focus::Preview,
+            _ => Focus::List,
+        };
+        self.visual_anchor = None;
+        // Wherever the list left the preview scrolled, the cursor starts on screen.
+        let last = self.preview_scroll + self.preview_height.get().saturating_sub(1);
+        self.preview_cursor = self.preview_cursor.clamp(self.preview_scroll, last.max(self.preview_scroll));
+    }
+
+    /// Replaces the selection with `preset`, returning the paths and globs
+    /// in it that matched nothing in the tree. A directory path selects
+    /// everything under it.
+    pub fn apply_preset(&mut self, preset: &Preset) -> Vec<String> {
+        for it in &mut self.items { it.set_selected(false); }
+        let mut missing = Vec::new();
+        for path in &preset.paths {
+            match self.items.iter().position(|e| e.rel == Path::new(path)) {
+                Some(i) => self.set_subtree(i, true),
+                None => missing.push(path.clone()),
+            }
+        }
+        for glob in &preset.globs {
+            let Ok(matcher) = Glob::new(glob).map(|g| g.compile_matcher()) else {
+                missing.push(format!("{} (invalid glob)", glob));
+                continue;
+            };
+            let mut matched = false;
+            for e in self.items.iter_mut().filter(|e| !e.is_dir && matcher.is_match(&e.rel)) {
+                e.selected = true;
+                matched = true;
+            }
+            if !matched { missing.push(glob.clone()); }
+        }
+        missing
+    }
+
+    /// Selected files as paths relative to the root, the way presets and the
+    /// remembered selection store them.
+    pub fn selected_rels(&self) -> Vec<String> {
+        self.selected_entries().iter().map(|e| e.rel.to_string_lossy().into_owned()).collect()
+    }
+
+    /// Reselects whatever was confirmed the last time sharkit ran here.
+    pub fn restore_selection(&mut self) {
+        let paths = state::load_selection(&self.root);
+        if paths.is_empty() { return; }
+        self.apply_preset(&Preset { paths, ..Preset::default() });
+        self.refresh_visible();
+        let count = self.selected_count();
+        if count > 0 {
+            self.status = Some(format!("restored {} files from the last run, [x] to clear", count));
+        }
+    }
+
+    pub fn forget_selection(&mut self) {
+        for it in &mut self.items { it.set_selected(false); }
+        self.status = Some(match state::clear_selection(&self.root) {
+            Ok(()) => "cleared the selection and forgot the last run's".to_string(),
+            Err(e) => format!("couldn't forget the last selection: {:#}", e),
+        });
+    }
+
+    pub fn start_save_preset(&mut self) {
+        self.prompt = Some(Prompt::SavePreset(String::new()));
+    }
+
+    pub fn save_preset(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            self.status = Some("preset name can't be empty".to_string());
+            return;
+        }
+        let paths = self.selected_rels();
+        let count = paths.len();
+        let preset = Preset { name: name.to_string(), paths, globs: Vec::new() };
+        self.status = Some(match presets::save(&self.root, preset) {
+            Ok(()) => format!("saved preset `{}` ({} files)", name, count),
+            Err(e) => format!("couldn't save preset: {:#}", e),
+        });
+    }
+
+    pub fn start_load_preset(&mut self) {
+        match presets::load(&self.root) {
+            Ok(presets) if presets.is_empty() => self.status = Some("no presets saved yet, press [s] to save one".to_string()),
+            Ok(presets) => self.prompt = Some(Prompt::LoadPreset { presets, cursor: 0 }),
+            Err(e) => self.status = Some(format!("{:#}", e)),
+        }
+    }
+
+    pub fn load_preset(&mut self, preset: &Preset) {
+        let missing = self.apply_preset(preset);
+        self.refresh_visible();
+        self.status = Some(if missing.is_empty() {
+            format!("loaded preset `{}` ({} files)", preset.name, self.selected_count())
+        } else {
+            format!("loaded preset `{}`, missing: {}", preset.name, missing.join(", "))
+        });
+    }
+
+    pub fn toggle_tree(&mut self) {
+        self.include_tree = !self.include_tree;
+    }
+
+    pub fn toggle_preview(&mut self) {
+        self.show_preview = !self.show_preview;
+        if !self.show_preview { self.focus = Focus::List; }
+    }
+}
+
+/// `ranges` plus `new`, kept sorted with overlapping or adjacent ranges joined.
+fn merge_range(ranges: &[(usize, usize)], new: (usize, usize)) -> Vec<(usize, usize)> {
+    let mut all = ranges.to_vec();
+    all.push(new);
+    all.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in all {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// `ranges` without the lines in `cut`.
+fn remove_range(ranges: &[(usize, usize)], cut: (usize, usize)) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    for &(start, end) in ranges {
+        if start < cut.0 { out.push((start, end.min(cut.0 - 1))); }
+        if end > cut.1 { out.push((start.max(cut.1 + 1), end)); }
+    }
+    out
+}
+
+/// `path: L3-L6, L11` for the status line.
+fn ranges_status(e: &Entry) -> String {
+    let labels: Vec<String> = e.ranges.iter().map(|&r| content::range_label(r)).collect();
+    format!("{}: {}", e.rel.display(), labels.join(", "))
+}
+
+/// Highlights what would be redacted in a freshly loaded chunk of preview.
+/// A match spanning two chunks is only marked where it's visible in each.
+fn mark_redactions(redactor: &Redactor, style: Style, text: &str, lines: &mut [Line<'static>]) {
+    if !redactor.is_active() { return; }
+    let spans = redactor.spans(text);
+    if spans.is_empty() { return; }
+    let mut start = 0;
+    for (line, raw) in lines.iter_mut().zip(text.split_inclusive('\n')) {
+        let end = start + raw.len();
+        let ranges: Vec<_> = spans.iter()
+            .filter(|(r, _)| r.start < end && r.end > start)
+            .map(|(r, _)| r.start.max(start) - start..r.end.min(end) - start)
+            .collect();
+        highlight::mark_ranges(line, raw, &ranges, style);
+        start = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::text::Span;
+
+    use crate::{cli::Options, listing::Filter};
+
+    /// `src/` holding `a.rs`, `b.rs` and `c.rs`, expanded, none of which exist
+    /// on disk.
+    fn app() -> App {
+        let root = PathBuf::from("/nonexistent/sharkit-test");
+        let entry = |name: &str, depth, parent, is_dir| {
+            let rel = if depth == 0 { PathBuf::from(name) } else { Path::new("src").join(name) };
+            Entry {
+                name: name.into(), path: root.join(&rel), rel, depth, parent, is_dir, expanded: is_dir, hidden: false, ignored: false,
+                selected: false, tokens: None, git: None, secrets: None, ranges: Vec::new(), skeleton: false,
+            }
+        };
+        let items = vec![entry("src", 0, None, true), entry("a.rs", 1, Some(0), false), entry("b.rs", 1, Some(0), false), entry("c.rs", 1, Some(0), false)];
+        let opts = Options { root: root.clone(), ..Options::default() };
+        let filter = Filter::new(&opts).unwrap();
+        App::new(items, &opts, &filter, Redactor::load(&root).unwrap())
+    }
+
+    fn symbol(start: usize, end: usize) -> Symbol {
+        Symbol { kind: "fn", name: format!("f{}", start), depth: 0, start, end, body: None }
+    }
+
+    #[test]
+    fn sideways_scrolling_stops_at_the_widest_line() {
+        let mut app = app();
+        app.preview_lines = vec![Line::from(vec![Span::raw("   1 │ "), Span::raw("let "), Span::raw("x = 1;")]), Line::raw("   2 │ ")];
+        app.scroll_preview_sideways(8);
+        assert_eq!(app.preview_column, 8);
+        app.scroll_preview_sideways(8);
+        assert_eq!(app.preview_column, 9);
+        app.scroll_preview_sideways(-20);
+        assert_eq!(app.preview_column, 0);
+    }
+
+    #[test]
+    fn merge_joins_overlapping_and_adjacent_ranges() {
+        assert_eq!(merge_range(&[], (5, 8)), [(5, 8)]);
+        assert_eq!(merge_range(&[(1, 3)], (4, 6)), [(1, 6)]);
+        assert_eq!(merge_range(&[(4, 6)], (1, 3)), [(1, 6)]);
+        assert_eq!(merge_range(&[(1, 3)], (5, 6)), [(1, 3), (5, 6)]);
+        assert_eq!(merge_range(&[(1, 3), (8, 9)], (2, 8)), [(1, 9)]);
+        assert_eq!(merge_range(&[(10, 12), (1, 2)], (5, 5)), [(1, 2), (5, 5), (10, 12)]);
+    }
+
+    #[test]
+    fn remove_cuts_ranges() {
+        assert_eq!(remove_range(&[(1, 10)], (4, 6)), [(1, 3), (7, 10)]);
+        assert_eq!(remove_range(&[(1, 10)], (1, 10)), []);
+        assert_eq!(remove_range(&[(1, 10)], (1, 4)), [(5, 10)]);
+        assert_eq!(remove_range(&[(1, 10)], (8, 20)), [(1, 7)]);
+        assert_eq!(remove_range(&[(1, 3), (5, 8), (10, 12)], (5, 8)), [(1, 3), (10, 12)]);
+        assert_eq!(remove_range(&[(1, 3)], (5, 8)), [(1, 3)]);
+    }
+
+    #[test]
+    fn symbols_toggle_as_ranges() {
+        let mut app = app();
+        app.cursor = 1;
+        app.outline = Some(vec![symbol(1, 3), symbol(4, 9), symbol(12, 20)]);
+        for i in 0..3 {
+            app.outline_cursor = i;
+            app.toggle_symbol();
+        }
+        assert_eq!(app.items[1].ranges, [(1, 9), (12, 20)]);
+        assert!(app.mark(1) == Mark::Partial);
+
+        app.outline_cursor = 1;
+        app.toggle_symbol();
+        assert_eq!(app.items[1].ranges, [(1, 3), (12, 20)]);
+        app.outline_cursor = 0;
+        app.toggle_symbol();
+        assert_eq!(app.items[1].ranges, [(12, 20)]);
+        assert!(app.items[1].selected);
+
+        // Unpicking the last range leaves nothing of the file selected.
+        app.outline_cursor = 2;
+        app.toggle_symbol();
+        assert!(app.items[1].ranges.is_empty());
+        assert!(!app.items[1].selected);
+        assert!(app.mark(1) == Mark::None);
+    }
+
+    #[test]
+    fn picking_a_symbol_overrides_the_skeleton() {
+        let mut app = app();
+        app.cursor = 1;
+        app.items[1].selected = true;
+        app.items[1].skeleton = true;
+        app.outline = Some(vec![symbol(4, 9)]);
+        app.toggle_symbol();
+        assert!(!app.items[1].skeleton);
+        assert!(app.mark(1) == Mark::Partial);
+    }
+
+    #[test]
+    fn space_toggles_full_ranged_and_skeleton_files() {
+        let mut app = app();
+        app.cursor = 1;
+        app.toggle_current();
+        assert!(app.mark(1) == Mark::All);
+        app.toggle_current();
+        assert!(app.mark(1) == Mark::None);
+
+        app.cursor = 2;
+        app.items[2].selected = true;
+        app.items[2].ranges = vec![(3, 7)];
+        assert!(app.mark(2) == Mark::Partial);
+        app.toggle_current();
+        assert!(app.mark(2) == Mark::None);
+        assert!(app.items[2].ranges.is_empty());
+        app.toggle_current();
+        assert!(app.mark(2) == Mark::All);
+
+        app.cursor = 3;
+        app.items[3].selected = true;
+        app.items[3].skeleton = true;
+        assert!(app.mark(3) == Mark::Skeleton);
+        app.toggle_current();
+        assert!(app.mark(3) == Mark::None);
+        // The file keeps its skeleton mode for when it comes back.
+        app.toggle_current();
+        assert!(app.mark(3) == Mark::Skeleton);
+    }
+
+    #[test]
+    fn space_on_a_directory_fills_then_clears_it() {
+        let mut app = app();
+        app.items[2].selected = true;
+        app.items[2].ranges = vec![(1, 2)];
+        assert!(app.mark(0) == Mark::Partial);
+        app.cursor = 0;
+        app.toggle_current();
+        assert!(app.mark(0) == Mark::All);
+        app.toggle_current();
+        assert!(app.mark(0) == Mark::None);
+        assert!(app.items[2].ranges.is_empty());
+    }
+}
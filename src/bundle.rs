@@ -0,0 +1,351 @@
+use std::{collections::HashMap, fmt::Write, fs, path::{Path, PathBuf}};
+
+use anyhow::{bail, Result};
+
+use crate::{content::{self, Kind}, git::{self, DiffBase, FileDiff}, lang, listing::{Entry, Part}, outline, redact::Redactor};
+
+pub fn rel_path(path: &Path, root: &Path) -> PathBuf {
+    pathdiff::diff_paths(path, root).unwrap_or_else(|| path.to_path_buf())
+}
+
+/// The files to emit only part of, keyed by path.
+pub type Parts = HashMap<PathBuf, Part>;
+
+/// What of `text` goes out for a file in `lang` with `part`; skeletons of
+/// languages without an outline stay whole.
+pub fn part_text(lang: Option<&str>, text: String, part: Option<&Part>) -> String {
+    match part {
+        Some(Part::Ranges(ranges)) => content::excerpt(&text, ranges),
+        Some(Part::Skeleton) => lang.and_then(|l| outline::skeleton(l, &text)).unwrap_or(text),
+        None => text,
+    }
+}
+
+/// Renders the files as one Markdown document: a table of contents, then
+/// each file under a `### path` heading in a fenced block tagged with its
+/// language. Files in `parts` get a `### path:L10-L80` section per range,
+/// or a `### path (skeleton)` one. `tree`, when given, goes first as a
+/// project overview.
+pub fn render_markdown(paths: &[PathBuf], root: &Path, parts: &Parts, tree: Option<&str>, redactor: &Redactor) -> String {
+    let rels: Vec<String> = paths.iter().map(|p| rel_path(p, root).to_string_lossy().into_owned()).collect();
+    let mut out = String::new();
+    push_tree_header(&mut out, tree);
+    out.push_str("## Files\n\n");
+    for (path, rel) in paths.iter().zip(&rels) {
+        let headings = match parts.get(path) {
+            Some(Part::Ranges(rs)) => rs.iter().map(|&r| format!("{}:{}", rel, content::range_label(r))).collect(),
+            Some(Part::Skeleton) => vec![format!("{} (skeleton)", rel)],
+            None => vec![rel.clone()],
+        };
+        for heading in headings {
+            let _ = writeln!(out, "- [{}](#{})", heading, anchor(&heading));
+        }
+    }
+    for (path, rel) in paths.iter().zip(&rels) {
+        let content = match content::read_text(path) {
+            Ok(content) => content,
+            Err(e) => {
+                let _ = writeln!(out, "\n### {}\n\n_could not read file: {}_", rel, e);
+                continue;
+            }
+        };
+        let lang = lang::detect_with_shebang(path, &content);
+        let rs = match parts.get(path) {
+            Some(Part::Ranges(rs)) => rs,
+            part => {
+                let skeleton = if part.is_some() { " (skeleton)" } else { "" };
+                let _ = writeln!(out, "\n### {}{}\n", rel, skeleton);
+                push_fenced(&mut out, lang.unwrap_or(""), &redactor.apply(rel, part_text(lang, content, part)));
+                continue;
+            }
+        };
+        let mut prev = None;
+        for (range, text) in content::line_ranges(&content, rs) {
+            if let Some(p) = prev { let _ = writeln!(out, "\n_{}_", content::elision(p, range)); }
+            let _ = writeln!(out, "\n### {}:{}\n", rel, content::range_label(range));
+            push_fenced(&mut out, lang.unwrap_or(""), &redactor.apply(rel, text));
+            prev = Some(range);
+        }
+    }
+    out
+}
+
+/// The `## Project tree` section that opens Markdown and hybrid bundles.
+fn push_tree_header(out: &mut String, tree: Option<&str>) {
+    let Some(tree) = tree else { return };
+    let fence = "`".repeat(fence_len(tree));
+    let _ = write!(out, "## Project tree\n\n{}\n{}{}\n\n", fence, tree, fence);
+}
+
+/// `body` in a code fence long enough to hold it, tagged with `lang`.
+fn push_fenced(out: &mut String, lang: &str, body: &str) {
+    let fence = "`".repeat(fence_len(body));
+    let _ = write!(out, "{}{}\n{}", fence, lang, body);
+    if !body.is_empty() && !body.ends_with('\n') { out.push('\n'); }
+    let _ = writeln!(out, "{}", fence);
+}
+
+/// Concatenates the unified diff of every changed file, ready for `git apply`.
+/// Returns the text and how many selected files had no changes.
+pub fn render_diff(paths: &[PathBuf], root: &Path, base: &DiffBase, context: usize, redactor: &Redactor) -> Result<(String, usize)> {
+    let mut out = String::new();
+    let mut unchanged = 0;
+    for path in paths {
+        let rel = rel_path(path, root);
+        match git::file_diff(root, &rel, base, context)? {
+            FileDiff::Unchanged => unchanged += 1,
+            FileDiff::New(diff) | FileDiff::Changed(diff) => out.push_str(&redactor.apply(&rel.to_string_lossy(), diff)),
+        }
+    }
+    Ok((out, unchanged))
+}
+
+/// Like `render_markdown`, but files that changed against `base` show up as a
+/// diff, and new ones in full. Unchanged files are left out.
+pub fn render_hybrid(paths: &[PathBuf], root: &Path, base: &DiffBase, context: usize, tree: Option<&str>, redactor: &Redactor) -> Result<(String, usize)> {
+    let mut sections = Vec::new();
+    let mut unchanged = 0;
+    for path in paths {
+        let rel = rel_path(path, root);
+        match git::file_diff(root, &rel, base, context)? {
+            FileDiff::Unchanged => unchanged += 1,
+            FileDiff::New(_) => {
+                let content = content::read_text(path)?;
+                let lang = lang::detect_with_shebang(path, &content).unwrap_or("");
+                let content = redactor.apply(&rel.to_string_lossy(), content);
+                sections.push((format!("{} (new)", rel.display()), lang, content));
+            }
+            FileDiff::Changed(diff) => sections.push((format!("{} (changed)", rel.display()), "diff", redactor.apply(&rel.to_string_lossy(), diff))),
+        }
+    }
+    let mut out = String::new();
+    push_tree_header(&mut out, tree);
+    let _ = write!(out, "## Changes against {}\n\n", base);
+    for (heading, _, _) in &sections {
+        let _ = writeln!(out, "- [{}](#{})", heading, anchor(heading));
+    }
+    for (heading, lang, body) in &sections {
+        let _ = write!(out, "\n### {}\n\n", heading);
+        push_fenced(&mut out, lang, body);
+    }
+    Ok((out, unchanged))
+}
+
+/// A fence must be longer than any backtick run inside the block, or the
+/// first ``` in the file would end it early.
+fn fence_len(content: &str) -> usize {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    (longest + 1).max(3)
+}
+
+/// GitHub's heading anchor: lowercase, spaces to dashes, other punctuation dropped.
+fn anchor(heading: &str) -> String {
+    heading.chars().filter_map(|c| match c {
+        ' ' => Some('-'),
+        c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c.to_ascii_lowercase()),
+        _ => None,
+    }).collect()
+}
+
+/// Which optional attributes `<file>` elements carry in XML output.
+#[derive(Clone, Copy)]
+pub struct XmlAttrs {
+    pub size: bool,
+    pub language: bool,
+    pub lines: bool,
+    /// Git blob object id, so a prompt can refer back to an exact revision.
+    pub hash: bool,
+}
+
+impl Default for XmlAttrs {
+    fn default() -> Self {
+        Self { size: true, language: true, lines: true, hash: false }
+    }
+}
+
+impl XmlAttrs {
+    /// Parses a comma-separated list such as `size,lines,hash`; `none` clears all.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut attrs = Self { size: false, language: false, lines: false, hash: false };
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match name {
+                "size" => attrs.size = true,
+                "language" => attrs.language = true,
+                "lines" => attrs.lines = true,
+                "hash" => attrs.hash = true,
+                "none" => {}
+                _ => bail!("unknown XML attribute `{}` (expected size, language, lines or hash)", name),
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+/// Renders the files as `<file path="...">` elements under a `<context>`
+/// root, with contents in CDATA so code needs no entity escaping. Files with
+/// line ranges in `parts` get an element per range, marked `range="10-80"`;
+/// skeletons are marked `skeleton="true"`. `tree`, when given, becomes a
+/// leading `<tree>` element.
+pub fn render_xml(paths: &[PathBuf], root: &Path, attrs: XmlAttrs, parts: &Parts, tree: Option<&str>, redactor: &Redactor) -> String {
+    let mut out = String::from("<context>\n");
+    if let Some(tree) = tree {
+        let _ = writeln!(out, "<tree>\n{}\n</tree>", cdata(tree));
+    }
+    for path in paths {
+        let rel = rel_path(path, root).to_string_lossy().into_owned();
+        let mut open = format!("<file path=\"{}\"", xml_escape(&rel));
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                let _ = writeln!(out, "{} error=\"{}\"/>", open, xml_escape(&e.to_string()));
+                continue;
+            }
+        };
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+        if attrs.size { let _ = write!(open, " size=\"{}\"", bytes.len()); }
+        let lang = lang::detect_with_shebang(path, &text);
+        if attrs.language {
+            if let Some(lang) = lang { let _ = write!(open, " language=\"{}\"", lang); }
+        }
+        if attrs.lines { let _ = write!(open, " lines=\"{}\"", text.lines().count()); }
+        if attrs.hash { let _ = write!(open, " blob=\"{}\"", content::git_blob_hash(&bytes)); }
+        let rs = match parts.get(path) {
+            Some(Part::Ranges(rs)) => rs,
+            part => {
+                if part.is_some() { open.push_str(" skeleton=\"true\""); }
+                let _ = write!(out, "{}>\n{}\n</file>\n", open, cdata(&redactor.apply(&rel, part_text(lang, text, part))));
+                continue;
+            }
+        };
+        let mut prev = None;
+        for ((start, end), part) in content::line_ranges(&text, rs) {
+            if let Some(p) = prev { let _ = writeln!(out, "<!-- {} -->", content::elision(p, (start, end))); }
+            let _ = write!(out, "{} range=\"{}-{}\">\n{}\n</file>\n", open, start, end, cdata(&redactor.apply(&rel, part)));
+            prev = Some((start, end));
+        }
+    }
+    out.push_str("</context>\n");
+    out
+}
+
+pub fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(xml_char(c)),
+        }
+    }
+    out
+}
+
+/// Wraps text in CDATA, splitting any `]]>` across two sections since it
+/// can't appear inside one.
+fn cdata(text: &str) -> String {
+    let body: String = text.chars().map(xml_char).collect();
+    format!("<![CDATA[{}]]>", body.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Control characters other than tab and newlines aren't allowed in XML 1.0 at all.
+fn xml_char(c: char) -> char {
+    if c.is_control() && !matches!(c, '\t' | '\n' | '\r') { char::REPLACEMENT_CHARACTER } else { c }
+}
+
+/// One JSON object per file, as an array or (`lines`) as JSON Lines. The
+/// schema is documented in the README; keep the two in sync.
+pub fn render_json(entries: &[&Entry], with_contents: bool, lines: bool, redactor: &Redactor) -> String {
+    let objects: Vec<String> = entries.iter().map(|e| json_object(e, with_contents, redactor)).collect();
+    if lines {
+        objects.iter().map(|o| format!("{}\n", o)).collect()
+    } else {
+        format!("[{}]\n", objects.join(","))
+    }
+}
+
+fn json_object(entry: &Entry, with_contents: bool, redactor: &Redactor) -> String {
+    let opt_str = |s: Option<&str>| s.map_or("null".to_string(), json_string);
+    let absolute = fs::canonicalize(&entry.path).unwrap_or_else(|_| entry.path.clone());
+    let size = fs::metadata(&entry.path).map_or(0, |m| m.len());
+    let binary = matches!(content::sniff_file(&entry.path), Ok(Kind::Binary));
+    let text = if binary { None } else { content::read_text(&entry.path).ok() };
+    let language = match &text {
+        Some(text) => lang::detect_with_shebang(&entry.path, text),
+        None => lang::detect(&entry.path),
+    };
+    let mut out = format!(
+        "{{\"path\":{},\"absolute\":{},\"size\":{},\"hidden\":{},\"ignored\":{},\"binary\":{},\"language\":{},\"tokens\":{}",
+        json_string(&entry.rel.to_string_lossy()),
+        json_string(&absolute.to_string_lossy()),
+        size,
+        entry.hidden,
+        entry.ignored,
+        binary,
+        opt_str(language),
+        entry.tokens.map_or("null".to_string(), |t| t.to_string()),
+    );
+    if entry.ranges.is_empty() {
+        out.push_str(",\"ranges\":null");
+    } else {
+        let ranges: Vec<String> = entry.ranges.iter().map(|(s, e)| format!("[{},{}]", s, e)).collect();
+        let _ = write!(out, ",\"ranges\":[{}]", ranges.join(","));
+    }
+    let _ = write!(out, ",\"skeleton\":{}", entry.skeleton && entry.ranges.is_empty());
+    if with_contents {
+        let text = text.map(|t| part_text(language, t, entry.part().as_ref()));
+        let text = text.map(|t| redactor.apply(&entry.rel.to_string_lossy(), t));
+        let _ = write!(out, ",\"contents\":{}", opt_str(text.as_deref()));
+    }
+    out.push('}');
+    out
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fence_outgrows_backtick_runs() {
+        assert_eq!(fence_len("plain text"), 3);
+        assert_eq!(fence_len("inline `code` only"), 3);
+        assert_eq!(fence_len("```rust\nfn main() {}\n```\n"), 4);
+        assert_eq!(fence_len("````\nnested ``` fence\n````\n"), 5);
+    }
+
+    #[test]
+    fn fenced_block_survives_inner_fences() {
+        let mut out = String::new();
+        push_fenced(&mut out, "markdown", "```\nx\n```");
+        assert_eq!(out, "````markdown\n```\nx\n```\n````\n");
+    }
+
+    #[test]
+    fn anchors_drop_dots_and_slashes() {
+        assert_eq!(anchor("src/main.rs"), "srcmainrs");
+        assert_eq!(anchor(".github/workflows/ci.yml"), "githubworkflowsciyml");
+        assert_eq!(anchor("docs/My Notes.v2.md"), "docsmy-notesv2md");
+        assert_eq!(anchor("src/app.rs:L10-L80"), "srcapprsl10-l80");
+        assert_eq!(anchor("src/lib_util.rs (skeleton)"), "srclib_utilrs-skeleton");
+    }
+}
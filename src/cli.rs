@@ -0,0 +1,222 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use crate::{bundle::XmlAttrs, git, tokens::{self, Tokenizer}, tree::TreeLimits};
+
+const USAGE: &str = "\
+Usage: sharkit [OPTIONS] [ROOT]
+
+Pick files from ROOT (default: the current directory) and print them.
+
+Options:
+  -o, --output <MODE>     what to emit: paths, markdown (alias: bundle), xml, json, jsonl,
+                          diff or hybrid [default: paths] (--format is an alias)
+      --contents          include file contents in json / jsonl output
+      --tree              start markdown / xml bundles with a tree of the project
+      --tree-depth <N>    levels of the tree to show [default: 3]
+      --tree-limit <N>    entries of the tree to show [default: 200]
+  -i, --include <GLOB>    select files matching GLOB up front (repeatable)
+  -e, --exclude <GLOB>    leave files matching GLOB out entirely (repeatable)
+      --skeleton <GLOB>   emit only the declarations of files matching GLOB, with
+                          function bodies left out (repeatable)
+      --modified          select files with unstaged changes
+      --staged            select files with staged changes
+      --untracked         select untracked files
+      --since <REF>       select files changed since REF (e.g. main), working tree included
+      --diff-base <BASE>  what diff / hybrid output compares against: head, index or a ref
+                          [default: the --since ref, or head]
+  -U, --diff-context <N>  lines of context around each diff hunk [default: 3]
+      --hidden            let hidden files be selected by --include / --non-interactive
+      --no-ignore         let gitignored files be selected by --include / --non-interactive
+      --redact-secrets    replace anything that looks like a credential with [REDACTED:<rule>]
+      --allow-secrets     emit files that look like they hold credentials without asking
+  -n, --non-interactive   skip the TUI and emit the selection straight away
+  -p, --preset <NAME>     emit a preset saved in .sharkit/presets.toml, without the TUI
+      --files-from <FILE> emit the files listed in FILE, one per line (`-` for stdin),
+                          without the TUI
+      --xml-attrs <LIST>  <file> attributes for xml: size, language, lines, hash or none
+                          [default: size,language,lines]
+      --budget <TOKENS>   context budget, e.g. 8000, 100k or 1.5m
+      --tokenizer <NAME>  token counter: cl100k (exact) or heuristic [default: cl100k]
+  -h, --help              print this help
+  -V, --version           print the version";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    /// One relative path per line, for shell pipelines.
+    Paths,
+    /// A Markdown document with every file in a code fence, ready to paste.
+    Markdown,
+    /// `<file>` elements under a `<context>` root, for prompt templates.
+    Xml,
+    /// A JSON array of per-file objects.
+    Json,
+    /// The same objects as `Json`, one per line.
+    JsonLines,
+    /// Unified diffs of the selected files against `--diff-base`.
+    Diff,
+    /// Markdown with whole new files and diffs of changed ones.
+    Hybrid,
+}
+
+impl OutputMode {
+    /// Whether the mode carries file contents rather than just names.
+    pub fn is_bundle(self) -> bool {
+        matches!(self, OutputMode::Markdown | OutputMode::Xml | OutputMode::Diff | OutputMode::Hybrid)
+    }
+
+    /// Whether output in this mode includes what's inside the files, given
+    /// `--contents` for the JSON modes.
+    pub fn carries_contents(self, json_contents: bool) -> bool {
+        self.is_bundle() || (json_contents && matches!(self, OutputMode::Json | OutputMode::JsonLines))
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "paths" => OutputMode::Paths,
+            "markdown" | "md" | "bundle" => OutputMode::Markdown,
+            "xml" => OutputMode::Xml,
+            "json" => OutputMode::Json,
+            "jsonl" | "ndjson" => OutputMode::JsonLines,
+            "diff" | "patch" => OutputMode::Diff,
+            "hybrid" => OutputMode::Hybrid,
+            _ => bail!("unknown output mode `{}` (expected paths, markdown, xml, json, jsonl, diff or hybrid)", s),
+        })
+    }
+}
+
+pub struct Options {
+    pub root: PathBuf,
+    pub output: OutputMode,
+    pub xml_attrs: XmlAttrs,
+    pub contents: bool,
+    pub tree: bool,
+    pub tree_limits: TreeLimits,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub skeleton: Vec<String>,
+    pub git_pick: git::Pick,
+    pub since: Option<String>,
+    pub diff_base: Option<git::DiffBase>,
+    pub diff_context: usize,
+    pub hidden: bool,
+    pub no_ignore: bool,
+    pub redact_secrets: bool,
+    pub allow_secrets: bool,
+    pub non_interactive: bool,
+    pub preset: Option<String>,
+    pub files_from: Option<String>,
+    pub budget: Option<usize>,
+    pub tokenizer: Tokenizer,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("."),
+            output: OutputMode::Paths,
+            xml_attrs: XmlAttrs::default(),
+            contents: false,
+            tree: false,
+            tree_limits: TreeLimits::default(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            skeleton: Vec::new(),
+            git_pick: git::Pick::default(),
+            since: None,
+            diff_base: None,
+            diff_context: 3,
+            hidden: false,
+            no_ignore: false,
+            redact_secrets: false,
+            allow_secrets: false,
+            non_interactive: false,
+            preset: None,
+            files_from: None,
+            budget: None,
+            tokenizer: Tokenizer::Cl100k,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_env() -> Result<Self> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut opts = Options::default();
+        let mut root = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`.
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => (f.to_string(), Some(v.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || -> Result<String> {
+                match inline.clone() {
+                    Some(v) => Ok(v),
+                    None => args.next().with_context(|| format!("{} expects a value", flag)),
+                }
+            };
+            match flag.as_str() {
+                "--contents" => opts.contents = true,
+                "--tree" => opts.tree = true,
+                "--tree-depth" => opts.tree_limits.depth = parse_count(&flag, &value()?)?,
+                "--tree-limit" => opts.tree_limits.entries = parse_count(&flag, &value()?)?,
+                "-o" | "--output" | "--format" => opts.output = OutputMode::parse(&value()?)?,
+                "--xml-attrs" => opts.xml_attrs = XmlAttrs::parse(&value()?)?,
+                "-i" | "--include" => opts.include.push(value()?),
+                "-e" | "--exclude" => opts.exclude.push(value()?),
+                "--skeleton" => opts.skeleton.push(value()?),
+                "--modified" => opts.git_pick.modified = true,
+                "--staged" => opts.git_pick.staged = true,
+                "--untracked" => opts.git_pick.untracked = true,
+                "--since" => {
+                    opts.since = Some(value()?);
+                    opts.git_pick.since = true;
+                }
+                "--diff-base" => opts.diff_base = Some(git::DiffBase::parse(&value()?)),
+                "-U" | "--diff-context" => opts.diff_context = parse_count(&flag, &value()?)?,
+                "--hidden" => opts.hidden = true,
+                "--no-ignore" => opts.no_ignore = true,
+                "--redact-secrets" => opts.redact_secrets = true,
+                "--allow-secrets" => opts.allow_secrets = true,
+                "-n" | "--non-interactive" => opts.non_interactive = true,
+                "-p" | "--preset" => opts.preset = Some(value()?),
+                "--files-from" => opts.files_from = Some(value()?),
+                "--budget" => opts.budget = Some(tokens::parse_budget(&value()?)?),
+                "--tokenizer" => opts.tokenizer = Tokenizer::parse(&value()?)?,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                "-V" | "--version" => {
+                    println!("sharkit {}", env!("CARGO_PKG_VERSION"));
+                    std::process::exit(0);
+                }
+                _ if flag.starts_with('-') => bail!("unknown option `{}`\n\n{}", flag, USAGE),
+                _ if root.is_none() => root = Some(PathBuf::from(arg)),
+                _ => bail!("unexpected argument `{}`\n\n{}", arg, USAGE),
+            }
+        }
+        if opts.preset.is_some() && opts.files_from.is_some() {
+            bail!("--preset and --files-from can't be combined");
+        }
+        // Reviewing "what changed since main" usually wants the diff against main too.
+        if opts.diff_base.is_none() {
+            opts.diff_base = Some(opts.since.as_deref().map_or(git::DiffBase::Head, git::DiffBase::parse));
+        }
+        if let Some(root) = root {
+            if !root.is_dir() { bail!("`{}` is not a directory", root.display()); }
+            opts.root = root;
+        }
+        Ok(opts)
+    }
+}
+
+fn parse_count(flag: &str, value: &str) -> Result<usize> {
+    value.parse().with_context(|| format!("{} expects a number, got `{}`", flag, value))
+}
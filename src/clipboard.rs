@@ -0,0 +1,121 @@
+use std::{env, fs::OpenOptions, io::{self, Write}, process::{Command, Stdio}};
+
+/// Terminals commonly drop OSC 52 payloads past roughly this size.
+const OSC52_MAX: usize = 100_000;
+
+/// Local copy tools tried, in order, when `SHARKIT_COPY_CMD` is unset.
+const COPY_COMMANDS: &[&str] = &[
+    "pbcopy",
+    "wl-copy",
+    "xclip -selection clipboard",
+    "xsel --clipboard --input",
+    "clip.exe",
+];
+
+pub enum Method {
+    Osc52,
+    Command(String),
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Method::Osc52 => write!(f, "OSC 52"),
+            Method::Command(cmd) => write!(f, "`{}`", cmd),
+        }
+    }
+}
+
+/// Puts `text` on the system clipboard. A command set in `SHARKIT_COPY_CMD`
+/// always wins. Locally the usual copy tools are tried first: writing OSC 52
+/// to the tty succeeds even on terminals that ignore it (VTE, Terminal.app),
+/// so it's only the fallback there. Over SSH, where those tools would fill
+/// the remote machine's clipboard, OSC 52 goes first unless the payload is
+/// too large for it.
+pub fn copy(text: &str) -> io::Result<Method> {
+    if let Ok(cmd) = env::var("SHARKIT_COPY_CMD") {
+        if !cmd.trim().is_empty() {
+            run_command(&cmd, text)?;
+            return Ok(Method::Command(cmd));
+        }
+    }
+    let encoded = base64(text.as_bytes());
+    let osc52 = || encoded.len() <= OSC52_MAX && write_osc52(&encoded).is_ok();
+    let remote = env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some();
+    if remote && osc52() {
+        return Ok(Method::Osc52);
+    }
+    for cmd in COPY_COMMANDS {
+        if run_command(cmd, text).is_ok() {
+            return Ok(Method::Command(cmd.to_string()));
+        }
+    }
+    if !remote && osc52() {
+        return Ok(Method::Osc52);
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "no clipboard available; set SHARKIT_COPY_CMD"))
+}
+
+fn write_osc52(encoded: &str) -> io::Result<()> {
+    let seq = format!("\x1b]52;c;{}\x07", encoded);
+    // tmux only forwards escape sequences wrapped in its passthrough DCS.
+    let seq = if env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", seq.replace('\x1b', "\x1b\x1b"))
+    } else {
+        seq
+    };
+    let mut tty = OpenOptions::new().write(true).open("/dev/tty")?;
+    tty.write_all(seq.as_bytes())?;
+    tty.flush()
+}
+
+fn run_command(cmd: &str, text: &str) -> io::Result<()> {
+    let mut parts = cmd.split_whitespace();
+    let program = parts.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty copy command"))?;
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().expect("piped stdin").write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("`{}` exited with {}", cmd, status)))
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(TABLE[(n >> 18) as usize & 63] as char);
+        out.push(TABLE[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { TABLE[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { TABLE[n as usize & 63] as char } else { '=' });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::base64;
+
+    #[test]
+    fn rfc4648_vectors() {
+        let cases = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+        for (input, want) in cases {
+            assert_eq!(base64(input.as_bytes()), want, "base64({:?})", input);
+        }
+    }
+
+    #[test]
+    fn high_bytes() {
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd]), "//79");
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+    }
+}
@@ -0,0 +1,21 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use anyhow::{Context, Result};
+
+/// `name` in the project's `.sharkit` directory, home of its presets and
+/// redaction rules.
+pub fn path(root: &Path, name: &str) -> PathBuf {
+    root.join(".sharkit").join(name)
+}
+
+/// Reads `.sharkit/<name>` and hands it to `parse`, or `None` when there's no
+/// such file. Errors name the file.
+pub fn load<T>(root: &Path, name: &str, parse: impl FnOnce(&str) -> Result<T>) -> Result<Option<T>> {
+    let path = path(root, name);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    parse(&text).map(Some).with_context(|| format!("reading {}", path.display()))
+}
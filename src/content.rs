@@ -0,0 +1,162 @@
+use std::{fs::File, io::{self, Read}, path::{Path, PathBuf}};
+
+use sha1::{Digest, Sha1};
+
+/// How much of a file is inspected to decide what it is.
+const SNIFF_LEN: usize = 8 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// Valid UTF-8.
+    Text,
+    /// Mostly text, but with some invalid UTF-8 that gets replaced with U+FFFD.
+    LossyText,
+    Binary,
+}
+
+/// Classifies by the first few KiB: any NUL byte, or a large share of bytes
+/// that are neither text nor decodable, means binary.
+pub fn sniff(bytes: &[u8]) -> Kind {
+    let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if head.contains(&0) {
+        return Kind::Binary;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => Kind::Text,
+        // Only the final char was cut off by the sniff window.
+        Err(e) if e.error_len().is_none() => Kind::Text,
+        Err(_) => {
+            let lossy = String::from_utf8_lossy(head);
+            let bad = lossy.chars().filter(|&c| c == char::REPLACEMENT_CHARACTER || (c.is_control() && !c.is_whitespace())).count();
+            if bad * 10 > lossy.chars().count() { Kind::Binary } else { Kind::LossyText }
+        }
+    }
+}
+
+pub fn sniff_file(path: &Path) -> io::Result<Kind> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?.take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+    Ok(sniff(&head))
+}
+
+/// Splits out binary files, which never belong in a pasted bundle.
+pub fn split_binaries(paths: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    paths.into_iter().partition(|p| !matches!(sniff_file(p), Ok(Kind::Binary)))
+}
+
+/// Reads a file as text, replacing invalid UTF-8 rather than failing.
+pub fn read_text(path: &Path) -> io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    })
+}
+
+/// Each of `ranges` (1-based, inclusive line numbers) with its text, cut
+/// short at the end of the file; ranges that start past it are dropped.
+pub fn line_ranges(text: &str, ranges: &[(usize, usize)]) -> Vec<((usize, usize), String)> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    ranges.iter().filter(|(start, _)| *start >= 1 && *start <= lines.len())
+        .map(|&(start, end)| ((start, end), lines[start - 1..end.min(lines.len())].concat()))
+        .collect()
+}
+
+/// Just the `ranges` of `text`, with an elision line between each two.
+pub fn excerpt(text: &str, ranges: &[(usize, usize)]) -> String {
+    let mut out = String::new();
+    let mut prev = None;
+    for (range, part) in line_ranges(text, ranges) {
+        if let Some(p) = prev { out.push_str(&elision(p, range)); out.push('\n'); }
+        out.push_str(&part);
+        if !part.ends_with('\n') { out.push('\n'); }
+        prev = Some(range);
+    }
+    out
+}
+
+/// `L10-L80`, or `L10` for a single line.
+pub fn range_label((start, end): (usize, usize)) -> String {
+    if start == end { format!("L{}", start) } else { format!("L{}-L{}", start, end) }
+}
+
+/// What goes between two ranges of the same file.
+pub fn elision(prev: (usize, usize), next: (usize, usize)) -> String {
+    let skipped = next.0.saturating_sub(prev.1 + 1);
+    format!("… {} line{} omitted …", skipped, if skipped == 1 { "" } else { "s" })
+}
+
+/// The longest prefix of `s` that fits in `max` bytes without splitting a char.
+pub fn truncate(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) { end -= 1; }
+    &s[..end]
+}
+
+/// `xxd`-style rows: offset, sixteen hex bytes, and the printable ASCII.
+pub fn hex_dump(bytes: &[u8], offset: u64) -> Vec<String> {
+    bytes.chunks(16).enumerate().map(|(row, chunk)| {
+        let mut hex = String::with_capacity(48);
+        for (i, b) in chunk.iter().enumerate() {
+            if i == 8 { hex.push(' '); }
+            hex.push_str(&format!("{:02x} ", b));
+        }
+        let ascii: String = chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+        format!("{:08x}  {:<49} |{}|", offset + row as u64 * 16, hex, ascii)
+    }).collect()
+}
+
+/// The object id git gives `bytes` as a blob: SHA-1 of `blob <len>\0<bytes>`.
+pub fn git_blob_hash(bytes: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", bytes.len()));
+    hasher.update(bytes);
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_hashes_match_git() {
+        assert_eq!(git_blob_hash(b""), "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391");
+        assert_eq!(git_blob_hash(b"hello\n"), "ce013625030ba8dba906f756967f9e9ca394464a");
+        assert_eq!(git_blob_hash(b"hello world"), "95d09f2b10159347eece71399a7e2e907ea3df4f");
+        assert_eq!(git_blob_hash(&[b'a'; 100_000]), "94bc76618de566c4e568aaf031cce7cef592d868");
+    }
+
+    #[test]
+    fn truncate_backs_off_to_a_char_boundary() {
+        assert_eq!(truncate("héllo", 2), "h");
+        assert_eq!(truncate("héllo", 3), "hé");
+        assert_eq!(truncate("a😀b", 4), "a");
+        assert_eq!(truncate("a😀b", 5), "a😀");
+        assert_eq!(truncate("😀", 0), "");
+        assert_eq!(truncate("short", 100), "short");
+    }
+
+    #[test]
+    fn sniff_classifies() {
+        assert_eq!(sniff(b""), Kind::Text);
+        assert_eq!(sniff("plain text, ünïcode too\n".as_bytes()), Kind::Text);
+        assert_eq!(sniff(b"text with a \0 in it"), Kind::Binary);
+        assert_eq!(sniff(b"latin-1: caf\xe9 cr\xe8me, mostly fine otherwise"), Kind::LossyText);
+        assert_eq!(sniff(&[0xff, 0xfe, 0x81, b'a', 0x90, 0xc0, b'b', 0xf8, 0x85, 0x9f]), Kind::Binary);
+    }
+
+    #[test]
+    fn sniff_ignores_a_char_cut_by_the_window() {
+        let mut bytes = vec![b'a'; SNIFF_LEN - 1];
+        bytes.extend_from_slice("é and more".as_bytes());
+        assert_eq!(sniff(&bytes), Kind::Text);
+        // A NUL past the window isn't seen.
+        bytes.extend_from_slice(&[0; 4]);
+        assert_eq!(sniff(&bytes), Kind::Text);
+        bytes[10] = 0xff;
+        assert_eq!(sniff(&bytes), Kind::LossyText);
+    }
+}
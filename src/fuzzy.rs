@@ -0,0 +1,71 @@
+/// Scores `candidate` against `pattern` as an in-order subsequence, returning
+/// the score and the char indices that matched, or `None` when it doesn't
+/// match. Matching is case-insensitive unless the pattern has an uppercase
+/// letter. Consecutive runs and matches at the start of a path component or
+/// word score higher, so `mr` ranks `main.rs` above `cmd/runner.go`.
+pub fn score(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let norm = |c: char| if case_sensitive { c } else { c.to_ascii_lowercase() };
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut positions = Vec::new();
+    let mut score = 0i64;
+    let mut next = 0;
+    for p in pattern.chars().filter(|c| !c.is_whitespace()).map(norm) {
+        let found = (next..chars.len()).find(|&i| norm(chars[i]) == p)?;
+        score += 1;
+        if positions.last().is_some_and(|&last| last + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(chars[found - 1], '/' | '_' | '-' | '.' | ' ') {
+            score += 3;
+        }
+        positions.push(found);
+        next = found + 1;
+    }
+    // Prefer matches in the file name over ones in parent directories, and
+    // shorter candidates over longer ones.
+    let name_start = chars.iter().rposition(|&c| c == '/').map_or(0, |i| i + 1);
+    score += positions.iter().filter(|&&i| i >= name_start).count() as i64 * 2;
+    score -= chars.len() as i64 / 16;
+    Some((score, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::score;
+
+    fn rank(pattern: &str, candidate: &str) -> i64 {
+        score(pattern, candidate).map_or(i64::MIN, |(s, _)| s)
+    }
+
+    #[test]
+    fn word_starts_rank_higher() {
+        assert!(rank("mr", "main.rs") > rank("mr", "cmd/runner.go"));
+        assert_eq!(score("mr", "main.rs").unwrap().1, vec![0, 5]);
+    }
+
+    #[test]
+    fn file_name_beats_directory() {
+        assert!(rank("app", "src/app.rs") > rank("app", "apps/web/index.ts"));
+    }
+
+    #[test]
+    fn subsequence_must_be_in_order() {
+        assert!(score("rm", "main.rs").is_none());
+        assert!(score("xyz", "main.rs").is_none());
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(score("readme", "README.md").is_some());
+        assert!(score("README", "README.md").is_some());
+        assert!(score("README", "readme.md").is_none());
+        assert!(score("Main", "main.rs").is_none());
+        assert!(score("Main", "src/Main.java").is_some());
+    }
+
+    #[test]
+    fn whitespace_in_pattern_is_ignored() {
+        assert_eq!(score("m r", "main.rs").unwrap().1, vec![0, 5]);
+    }
+}
@@ -0,0 +1,225 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, process::Command};
+
+use anyhow::{bail, Context, Result};
+
+use crate::listing::Entry;
+
+/// What git has to say about a file: where it's changed and the letter to
+/// show for it in the list.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Change {
+    /// Differs between HEAD and the index.
+    pub staged: bool,
+    /// Differs between the index and the working tree.
+    pub modified: bool,
+    pub untracked: bool,
+    /// Differs between the `--since` ref and the working tree.
+    pub since: bool,
+    /// M, A, ?, or • on directories with changes somewhere below.
+    pub marker: char,
+}
+
+/// Which changes to select up front (`--modified`, `--staged`, ...).
+#[derive(Clone, Copy, Default)]
+pub struct Pick {
+    pub modified: bool,
+    pub staged: bool,
+    pub untracked: bool,
+    pub since: bool,
+}
+
+impl Pick {
+    pub fn any(&self) -> bool {
+        self.modified || self.staged || self.untracked || self.since
+    }
+    pub fn matches(&self, c: &Change) -> bool {
+        (self.modified && c.modified) || (self.staged && c.staged) || (self.untracked && c.untracked) || (self.since && c.since)
+    }
+}
+
+/// Runs git in `root` and returns its stdout, or `None` if git is missing or
+/// fails (e.g. outside a repository).
+fn git(root: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let out = Command::new("git").arg("-C").arg(root).args(args).output().ok()?;
+    out.status.success().then_some(out.stdout)
+}
+
+/// Whether `r` names a commit in the repository at `root`. Anything that
+/// isn't one, including an option like `--output=…`, never reaches `git diff`.
+pub fn is_commit(root: &Path, r: &str) -> bool {
+    git(root, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", r)]).is_some()
+}
+
+/// Fills in `Entry::git` from the local repository. Returns false when `root`
+/// isn't inside a git work tree; `since` is a ref to diff the working tree
+/// against, and it's an error for it not to resolve.
+pub fn annotate(root: &Path, items: &mut [Entry], since: Option<&str>) -> Result<bool> {
+    let Some(prefix) = git(root, &["rev-parse", "--show-prefix"]) else {
+        if let Some(r) = since { bail!("--since {} needs {} to be inside a git repository", r, root.display()); }
+        return Ok(false);
+    };
+    let prefix = String::from_utf8_lossy(&prefix).trim().to_string();
+    let status = git(root, &["status", "--porcelain=v1", "-z", "--untracked-files=all"]).context("running git status")?;
+    let mut changes = parse_status(&status, &prefix);
+
+    if let Some(r) = since {
+        if !is_commit(root, r) {
+            bail!("--since: `{}` isn't a commit, branch or tag in this repository", r);
+        }
+        let diff = git(root, &["diff", "--name-status", "--no-renames", "--relative", "-z", r, "--"])
+            .with_context(|| format!("running git diff {}", r))?;
+        let mut fields = diff.split(|&b| b == 0).filter(|f| !f.is_empty());
+        while let (Some(kind), Some(path)) = (fields.next(), fields.next()) {
+            let c = changes.entry(PathBuf::from(String::from_utf8_lossy(path).as_ref())).or_default();
+            c.since = true;
+            if c.marker == '\0' { c.marker = status_marker(kind[0] as char); }
+        }
+    }
+
+    for i in 0..items.len() {
+        let Some(&c) = changes.get(&items[i].rel) else { continue };
+        items[i].git = Some(c);
+        let mut parent = items[i].parent;
+        while let Some(p) = parent {
+            if items[p].git.is_some() { break; }
+            items[p].git = Some(Change { marker: '•', ..Change::default() });
+            parent = items[p].parent;
+        }
+    }
+    Ok(true)
+}
+
+/// Reads `git status --porcelain=v1 -z` output. Porcelain paths are relative
+/// to the top of the repository, so only those under `prefix` are kept, with
+/// it stripped. A rename or copy is followed by a second record with the old
+/// path, which must be skipped whatever its length.
+fn parse_status(status: &[u8], prefix: &str) -> HashMap<PathBuf, Change> {
+    let mut changes: HashMap<PathBuf, Change> = HashMap::new();
+    let mut records = status.split(|&b| b == 0);
+    while let Some(rec) = records.next() {
+        if rec.len() < 4 { continue; }
+        let (x, y) = (rec[0] as char, rec[1] as char);
+        if matches!(x, 'R' | 'C') { records.next(); }
+        let path = String::from_utf8_lossy(&rec[3..]);
+        let Some(rel) = path.strip_prefix(prefix) else { continue };
+        let c = changes.entry(PathBuf::from(rel)).or_default();
+        if x == '?' {
+            c.untracked = true;
+        } else {
+            c.staged |= x != ' ';
+            c.modified |= y != ' ';
+        }
+        c.marker = status_marker(if x == '?' { '?' } else if x != ' ' { x } else { y });
+    }
+    changes
+}
+
+/// Deleted paths have no row in the list, so there is no marker for them.
+fn status_marker(letter: char) -> char {
+    match letter {
+        'A' | 'R' | 'C' => 'A',
+        '?' => '?',
+        _ => 'M',
+    }
+}
+
+/// What `--output diff` compares the working tree against.
+#[derive(Clone, PartialEq)]
+pub enum DiffBase {
+    Head,
+    Index,
+    Ref(String),
+}
+
+impl DiffBase {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "head" | "HEAD" => DiffBase::Head,
+            "index" | "staged" => DiffBase::Index,
+            r => DiffBase::Ref(r.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for DiffBase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DiffBase::Head => f.write_str("HEAD"),
+            DiffBase::Index => f.write_str("the index"),
+            DiffBase::Ref(r) => f.write_str(r),
+        }
+    }
+}
+
+pub enum FileDiff {
+    Unchanged,
+    /// Not in the base at all; the diff adds every line.
+    New(String),
+    Changed(String),
+}
+
+/// The unified diff of one file (relative to `root`) against `base`, with
+/// `context` lines around each hunk. Untracked files diff against nothing.
+pub fn file_diff(root: &Path, rel: &Path, base: &DiffBase, context: usize) -> Result<FileDiff> {
+    let unified = format!("-U{}", context);
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff", "--relative", unified.as_str()];
+    if let DiffBase::Ref(r) = base { args.push(r); }
+    if *base == DiffBase::Head { args.push("HEAD"); }
+    args.push("--");
+    let rel_str = rel.to_string_lossy();
+    args.push(&rel_str);
+    let out = git(root, &args).with_context(|| format!("git diff against {} failed in {}", base, root.display()))?;
+    if !out.is_empty() {
+        let diff = String::from_utf8_lossy(&out).into_owned();
+        return Ok(if diff.contains("\nnew file mode ") { FileDiff::New(diff) } else { FileDiff::Changed(diff) });
+    }
+    if git(root, &["ls-files", "--error-unmatch", "--", &rel_str]).is_some() {
+        return Ok(FileDiff::Unchanged);
+    }
+    // `--no-index` exits with 1 when the files differ, which is the point.
+    let out = Command::new("git").arg("-C").arg(root)
+        .args(["diff", "--no-color", "--no-ext-diff", "--no-index", &unified, "--", "/dev/null", &rel_str])
+        .output().context("running git diff --no-index")?;
+    if out.status.code() != Some(1) { bail!("git diff --no-index failed for {}", rel.display()); }
+    Ok(FileDiff::New(String::from_utf8_lossy(&out.stdout).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(changes: &HashMap<PathBuf, Change>) -> Vec<String> {
+        let mut v: Vec<String> = changes.keys().map(|p| p.display().to_string()).collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn short_old_path_of_a_rename_is_skipped() {
+        // `git mv ab abc_renamed` plus a staged zz.txt.
+        let changes = parse_status(b"R  abc_renamed\0ab\0A  zz.txt\0", "");
+        assert_eq!(paths(&changes), ["abc_renamed", "zz.txt"]);
+        assert!(changes[Path::new("zz.txt")].staged);
+        assert_eq!(changes[Path::new("abc_renamed")].marker, 'A');
+    }
+
+    #[test]
+    fn status_letters() {
+        let changes = parse_status(b" M src/a.rs\0MM src/b.rs\0?? new.txt\0C  copy.rs\0orig.rs\0 D gone.rs\0", "");
+        let a = changes[Path::new("src/a.rs")];
+        assert!(a.modified && !a.staged && a.marker == 'M');
+        let b = changes[Path::new("src/b.rs")];
+        assert!(b.modified && b.staged);
+        let new = changes[Path::new("new.txt")];
+        assert!(new.untracked && new.marker == '?');
+        assert_eq!(changes[Path::new("copy.rs")].marker, 'A');
+        assert!(!changes.contains_key(Path::new("orig.rs")));
+        assert_eq!(changes[Path::new("gone.rs")].marker, 'M');
+    }
+
+    #[test]
+    fn paths_outside_the_prefix_are_dropped() {
+        let changes = parse_status(b" M sub/x.rs\0 M other/y.rs\0R  sub/new.rs\0other/old.rs\0", "sub/");
+        assert_eq!(paths(&changes), ["new.rs", "x.rs"]);
+    }
+}
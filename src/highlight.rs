@@ -0,0 +1,409 @@
+use std::{env, ops::Range};
+
+use ratatui::prelude::*;
+
+use crate::content;
+
+/// Longer lines (minified bundles, data blobs) are cut for display.
+const MAX_LINE: usize = 2_000;
+
+/// Colours for each token class, picked once from what the terminal
+/// advertises so we don't emit truecolor escapes to an 8-colour console.
+#[derive(Clone)]
+pub struct Palette {
+    keyword: Style,
+    string: Style,
+    comment: Style,
+    number: Style,
+    type_name: Style,
+    gutter: Style,
+    redacted: Style,
+}
+
+impl Palette {
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        let plain = Style::default();
+        if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return Self {
+                keyword: plain.add_modifier(Modifier::BOLD),
+                string: plain,
+                comment: plain.add_modifier(Modifier::ITALIC),
+                number: plain,
+                type_name: plain,
+                gutter: plain.add_modifier(Modifier::DIM),
+                redacted: plain.add_modifier(Modifier::REVERSED),
+            };
+        }
+        let (keyword, string, comment, number, type_name, gutter) = if colorterm == "truecolor" || colorterm == "24bit" {
+            (Color::Rgb(198, 120, 221), Color::Rgb(152, 195, 121), Color::Rgb(106, 115, 125), Color::Rgb(209, 154, 102), Color::Rgb(229, 192, 123), Color::Rgb(92, 99, 112))
+        } else if term.contains("256color") {
+            (Color::Indexed(176), Color::Indexed(114), Color::Indexed(244), Color::Indexed(173), Color::Indexed(180), Color::Indexed(240))
+        } else {
+            (Color::Magenta, Color::Green, Color::DarkGray, Color::Yellow, Color::Cyan, Color::DarkGray)
+        };
+        Self {
+            keyword: plain.fg(keyword).add_modifier(Modifier::BOLD),
+            string: plain.fg(string),
+            comment: plain.fg(comment).add_modifier(Modifier::ITALIC),
+            number: plain.fg(number),
+            type_name: plain.fg(type_name),
+            gutter: plain.fg(gutter),
+            redacted: plain.fg(Color::Black).bg(Color::Red),
+        }
+    }
+    /// How text that will be redacted on output is shown in the preview.
+    pub fn redacted(&self) -> Style {
+        self.redacted
+    }
+}
+
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    /// The quotes whose strings may run on past the end of a line; any other
+    /// string left open there is taken as a stray quote and closed.
+    multiline_quotes: &'static [char],
+    keywords: &'static [&'static str],
+}
+
+const C_LIKE_COMMENTS: &[&str] = &["//"];
+const HASH_COMMENTS: &[&str] = &["#"];
+
+fn syntax(lang: &str) -> Option<Syntax> {
+    let (line_comments, block_comment, quotes, keywords): (_, _, &[char], &[&str]) = match lang {
+        "rust" => (C_LIKE_COMMENTS, Some(("/*", "*/")), &['"', '\''], &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for",
+            "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static",
+            "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+        ]),
+        "python" => (HASH_COMMENTS, None, &['"', '\''], &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else", "except",
+            "False", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "None", "nonlocal", "not", "or",
+            "pass", "raise", "return", "self", "True", "try", "while", "with", "yield",
+        ]),
+        "javascript" | "jsx" | "typescript" | "tsx" => (C_LIKE_COMMENTS, Some(("/*", "*/")), &['"', '\'', '`'], &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete", "else", "enum",
+            "export", "extends", "false", "finally", "for", "from", "function", "if", "implements", "import", "in",
+            "instanceof", "interface", "let", "new", "null", "of", "private", "protected", "public", "readonly", "return",
+            "static", "super", "switch", "this", "throw", "true", "try", "type", "typeof", "undefined", "var", "void", "while",
+            "yield",
+        ]),
+        "go" => (C_LIKE_COMMENTS, Some(("/*", "*/")), &['"', '\'', '`'], &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "false", "for", "func",
+            "go", "goto", "if", "import", "interface", "map", "nil", "package", "range", "return", "select", "struct",
+            "switch", "true", "type", "var",
+        ]),
+        "c" | "cpp" | "csharp" | "java" | "kotlin" | "swift" => (C_LIKE_COMMENTS, Some(("/*", "*/")), &['"', '\''], &[
+            "auto", "bool", "break", "case", "catch", "char", "class", "const", "continue", "default", "delete", "do",
+            "double", "else", "enum", "extern", "false", "float", "for", "fun", "func", "goto", "if", "import", "include",
+            "inline", "int", "interface", "let", "long", "namespace", "new", "null", "nullptr", "package", "private",
+            "protected", "public", "return", "short", "signed", "sizeof", "static", "struct", "switch", "template", "this",
+            "throw", "true", "try", "typedef", "union", "unsigned", "using", "val", "var", "virtual", "void", "volatile",
+            "while",
+        ]),
+        "bash" | "fish" => (HASH_COMMENTS, None, &['"', '\''], &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if", "in", "local", "return",
+            "set", "then", "until", "while",
+        ]),
+        "ruby" => (HASH_COMMENTS, None, &['"', '\''], &[
+            "begin", "class", "def", "do", "else", "elsif", "end", "ensure", "false", "if", "module", "nil", "require",
+            "rescue", "return", "self", "then", "true", "unless", "until", "when", "while", "yield",
+        ]),
+        "lua" => (&["--"], None, &['"', '\''], &[
+            "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local", "nil", "not",
+            "or", "repeat", "return", "then", "true", "until", "while",
+        ]),
+        "sql" => (&["--"], Some(("/*", "*/")), &['\''], &[
+            "and", "as", "by", "create", "delete", "from", "group", "having", "insert", "into", "join", "left", "limit",
+            "not", "null", "on", "or", "order", "select", "set", "table", "update", "values", "where",
+        ]),
+        "toml" | "yaml" | "makefile" | "dockerfile" | "cmake" | "nix" | "elixir" => (HASH_COMMENTS, None, &['"', '\''], &[]),
+        "css" | "scss" => (&[], Some(("/*", "*/")), &['"', '\''], &[]),
+        "html" | "xml" | "vue" | "svelte" => (&[], Some(("<!--", "-->")), &['"'], &[]),
+        "json" => (&[], None, &['"'], &["true", "false", "null"]),
+        _ => return None,
+    };
+    let multiline_quotes: &[char] = match lang {
+        "rust" => &['"'],
+        "javascript" | "jsx" | "typescript" | "tsx" | "go" => &['`'],
+        _ => &[],
+    };
+    Some(Syntax { line_comments, block_comment, quotes, multiline_quotes, keywords })
+}
+
+/// Highlights a file a chunk of whole lines at a time, carrying open block
+/// comments and strings across chunks, so large files can be loaded lazily.
+pub struct Highlighter {
+    syntax: Option<Syntax>,
+    rust: bool,
+    palette: Palette,
+    state: State,
+    next_line: usize,
+}
+
+impl Highlighter {
+    /// `lang` of `None` renders plain text, still with the line-number gutter.
+    pub fn new(lang: Option<&str>, palette: &Palette) -> Self {
+        Self { syntax: lang.and_then(syntax), rust: lang == Some("rust"), palette: palette.clone(), state: State::Code, next_line: 1 }
+    }
+
+    pub fn lines(&mut self, text: &str) -> Vec<Line<'static>> {
+        text.lines().map(|full| {
+            let line = content::truncate(full, MAX_LINE);
+            let mut spans = vec![Span::styled(format!("{:>4} │ ", self.next_line), self.palette.gutter)];
+            self.next_line += 1;
+            match &self.syntax {
+                Some(syntax) => spans.extend(highlight_line(line, syntax, self.rust, &self.palette, &mut self.state)),
+                None => spans.push(Span::raw(line.to_string())),
+            }
+            if line.len() < full.len() {
+                spans.push(Span::styled(" …", self.palette.gutter));
+            }
+            Line::from(spans)
+        }).collect()
+    }
+}
+
+/// What an unterminated construct at the end of a line carries into the next.
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Code,
+    BlockComment,
+    String(char),
+}
+
+fn highlight_line(line: &str, syntax: &Syntax, rust: bool, palette: &Palette, state: &mut State) -> Vec<Span<'static>> {
+    let chars: Vec<char> = line.chars().collect();
+    let starts_with = |i: usize, pat: &str| pat.chars().enumerate().all(|(k, c)| chars.get(i + k) == Some(&c));
+    let text = |a: usize, b: usize| chars[a..b].iter().collect::<String>();
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+    let flush = |spans: &mut Vec<Span<'static>>, from: usize, to: usize| {
+        if to > from { spans.push(Span::raw(text(from, to))); }
+    };
+
+    while i < chars.len() {
+        match *state {
+            State::BlockComment => {
+                let (_, close) = syntax.block_comment.expect("block comment state without delimiters");
+                let end = (i..chars.len()).find(|&j| starts_with(j, close)).map(|j| j + close.chars().count());
+                let stop = end.unwrap_or(chars.len());
+                spans.push(Span::styled(text(i, stop), palette.comment));
+                if end.is_some() { *state = State::Code; }
+                i = stop;
+                plain_start = i;
+                continue;
+            }
+            State::String(q) => {
+                let mut j = i;
+                while j < chars.len() && chars[j] != q {
+                    j += if chars[j] == '\\' { 2 } else { 1 };
+                }
+                let stop = (j + 1).min(chars.len());
+                spans.push(Span::styled(text(i, stop), palette.string));
+                if j < chars.len() { *state = State::Code; }
+                i = stop;
+                plain_start = i;
+                continue;
+            }
+            State::Code => {}
+        }
+
+        let c = chars[i];
+        if syntax.line_comments.iter().any(|p| starts_with(i, p)) {
+            flush(&mut spans, plain_start, i);
+            spans.push(Span::styled(text(i, chars.len()), palette.comment));
+            return spans;
+        }
+        if let Some((open, _)) = syntax.block_comment.filter(|(open, _)| starts_with(i, open)) {
+            flush(&mut spans, plain_start, i);
+            spans.push(Span::styled(text(i, i + open.chars().count()), palette.comment));
+            i += open.chars().count();
+            plain_start = i;
+            *state = State::BlockComment;
+            continue;
+        }
+        // In Rust a lone `'` is usually a lifetime, not a char literal.
+        let lifetime = rust && c == '\'' && chars.get(i + 1) != Some(&'\\') && chars.get(i + 2) != Some(&'\'');
+        if syntax.quotes.contains(&c) && !lifetime {
+            flush(&mut spans, plain_start, i);
+            spans.push(Span::styled(c.to_string(), palette.string));
+            i += 1;
+            plain_start = i;
+            *state = State::String(c);
+            continue;
+        }
+        if c.is_ascii_digit() && (i == 0 || !is_ident(chars[i - 1])) {
+            flush(&mut spans, plain_start, i);
+            let end = (i..chars.len()).find(|&j| !(chars[j].is_ascii_alphanumeric() || chars[j] == '.' || chars[j] == '_')).unwrap_or(chars.len());
+            spans.push(Span::styled(text(i, end), palette.number));
+            i = end;
+            plain_start = i;
+            continue;
+        }
+        if is_ident(c) && (i == 0 || !is_ident(chars[i - 1])) {
+            let end = (i..chars.len()).find(|&j| !is_ident(chars[j])).unwrap_or(chars.len());
+            let word = text(i, end);
+            let style = if syntax.keywords.contains(&word.as_str()) {
+                Some(palette.keyword)
+            } else if c.is_uppercase() && word.chars().any(|c| c.is_lowercase()) {
+                Some(palette.type_name)
+            } else {
+                None
+            };
+            if let Some(style) = style {
+                flush(&mut spans, plain_start, i);
+                spans.push(Span::styled(word, style));
+                plain_start = end;
+            }
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+    flush(&mut spans, plain_start, chars.len());
+    if matches!(*state, State::String(q) if !syntax.multiline_quotes.contains(&q)) {
+        *state = State::Code;
+    }
+    spans
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Restyles the parts of a highlighted line that fall in `ranges`, byte
+/// offsets into `text`, the line it was highlighted from (the gutter doesn't
+/// count). Whatever `lines` cut off a long line can't be marked, and the ` …`
+/// standing in for it is left alone.
+pub fn mark_ranges(line: &mut Line<'static>, text: &str, ranges: &[Range<usize>], style: Style) {
+    let shown = content::truncate(text, MAX_LINE).len();
+    let ranges: Vec<_> = ranges.iter().map(|r| r.start.min(shown)..r.end.min(shown)).filter(|r| !r.is_empty()).collect();
+    if ranges.is_empty() { return; }
+    let mut spans = std::mem::take(&mut line.spans).into_iter();
+    let mut out: Vec<Span<'static>> = spans.next().into_iter().collect();
+    let mut pos = 0;
+    for span in spans {
+        if pos >= shown {
+            out.push(span);
+            continue;
+        }
+        let (start, end) = (pos, pos + span.content.len());
+        pos = end;
+        let mut cuts: Vec<usize> = ranges.iter().flat_map(|r| [r.start, r.end]).filter(|&b| b > start && b < end).collect();
+        cuts.sort_unstable();
+        cuts.push(end);
+        let mut from = start;
+        for cut in cuts {
+            if cut <= from { continue; }
+            let inside = ranges.iter().any(|r| r.start <= from && from < r.end);
+            let piece = span.content[from - start..cut - start].to_string();
+            out.push(Span::styled(piece, if inside { span.style.patch(style) } else { span.style }));
+            from = cut;
+        }
+    }
+    line.spans = out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> Palette {
+        let plain = Style::default();
+        Palette {
+            keyword: plain.fg(Color::Magenta),
+            string: plain.fg(Color::Green),
+            comment: plain.fg(Color::DarkGray),
+            number: plain.fg(Color::Yellow),
+            type_name: plain.fg(Color::Cyan),
+            gutter: plain.fg(Color::Gray),
+            redacted: plain.bg(Color::Red),
+        }
+    }
+
+    /// The text of `line`'s spans in `style`, gutter left out.
+    fn styled(line: &Line, style: Style) -> String {
+        line.spans[1..].iter().filter(|s| s.style == style).map(|s| s.content.as_ref()).collect()
+    }
+
+    /// The string-coloured text on each line of `text`.
+    fn strings(lang: &str, text: &str) -> Vec<String> {
+        let p = palette();
+        Highlighter::new(Some(lang), &p).lines(text).iter().map(|l| styled(l, p.string)).collect()
+    }
+
+    #[test]
+    fn stray_quotes_close_at_the_end_of_the_line() {
+        assert_eq!(strings("yaml", "title: don't panic\nname: 'x'"), ["'t panic", "'x'"]);
+        assert_eq!(strings("bash", "echo it's\nls -la"), ["'s", ""]);
+        assert_eq!(strings("python", "s = \"open\nx = 1"), ["\"open", ""]);
+    }
+
+    #[test]
+    fn multiline_strings_carry_over() {
+        assert_eq!(strings("rust", "let s = \"one\ntwo\";\nlet t = 3;"), ["\"one", "two\"", ""]);
+        assert_eq!(strings("typescript", "const q = `select\nfrom t`;\nconst n = 'x"), ["`select", "from t`", "'x"]);
+        assert_eq!(strings("go", "s := `a\nb`"), ["`a", "b`"]);
+        assert_eq!(strings("typescript", "a = 'open\nb = 1"), ["'open", ""]);
+    }
+
+    #[test]
+    fn escapes_and_lifetimes() {
+        assert_eq!(strings("rust", r#"let s = "a \" b"; c"#), [r#""a \" b""#]);
+        assert_eq!(strings("rust", "fn f<'a>(x: &'a str) -> char { '\\n' }"), ["'\\n'"]);
+        assert_eq!(strings("rust", "let c = 'x';"), ["'x'"]);
+    }
+
+    #[test]
+    fn comments_span_lines_and_chunks() {
+        let p = palette();
+        let mut h = Highlighter::new(Some("c"), &p);
+        let first = h.lines("int a; /* start\nstill");
+        let second = h.lines("end */ int b; // \"tail");
+        assert_eq!(styled(&first[0], p.comment), "/* start");
+        assert_eq!(styled(&first[1], p.comment), "still");
+        assert_eq!(styled(&second[0], p.comment), "end */// \"tail");
+        assert_eq!(styled(&second[0], p.keyword), "int");
+        assert_eq!(second[0].spans[0].content, "   3 │ ");
+    }
+
+    #[test]
+    fn keywords_numbers_and_types() {
+        let p = palette();
+        let line = Highlighter::new(Some("rust"), &p).lines("let x: Vec<u8> = vec![0x1f, 2];").remove(0);
+        assert_eq!(styled(&line, p.keyword), "let");
+        assert_eq!(styled(&line, p.type_name), "Vec");
+        assert_eq!(styled(&line, p.number), "0x1f2");
+    }
+
+    #[test]
+    fn marks_stop_at_the_cut_of_a_long_line() {
+        let p = palette();
+        let text = "a".repeat(2100);
+        for end in [2001, 2002, 2003, 2100] {
+            let mut line = Highlighter::new(None, &p).lines(&text).remove(0);
+            mark_ranges(&mut line, &text, &[5..10, 1990..end], p.redacted);
+            assert_eq!(styled(&line, p.redacted), "a".repeat(15));
+            assert_eq!(line.spans.last().unwrap().content, " …");
+        }
+        let mut line = Highlighter::new(None, &p).lines(&text).remove(0);
+        mark_ranges(&mut line, &text, &[2050..2060, 2090..2100], p.redacted);
+        assert_eq!(styled(&line, p.redacted), "");
+    }
+
+    #[test]
+    fn marks_split_highlighted_spans() {
+        let p = palette();
+        let text = r#"let key = "sk-123";"#;
+        let mut line = Highlighter::new(Some("rust"), &p).lines(text).remove(0);
+        mark_ranges(&mut line, text, &[4..7, 11..17], p.redacted);
+        assert_eq!(styled(&line, p.redacted), "key");
+        assert_eq!(styled(&line, p.string.patch(p.redacted)), "sk-123");
+        assert_eq!(line.spans[1..].iter().map(|s| s.content.as_ref()).collect::<String>(), text);
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+
+/// Code fence tag for a file, guessed from its extension or well-known name.
+pub fn detect(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    match name {
+        "Makefile" | "makefile" | "GNUmakefile" => return Some("makefile"),
+        "Dockerfile" => return Some("dockerfile"),
+        "CMakeLists.txt" => return Some("cmake"),
+        "Cargo.lock" => return Some("toml"),
+        _ => {}
+    }
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let lang = match ext.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "go" => "go",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "rb" => "ruby",
+        "php" => "php",
+        "lua" => "lua",
+        "sh" | "bash" | "zsh" => "bash",
+        "fish" => "fish",
+        "ps1" => "powershell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "xml" => "xml",
+        "md" | "markdown" => "markdown",
+        "hs" => "haskell",
+        "ex" | "exs" => "elixir",
+        "erl" => "erlang",
+        "zig" => "zig",
+        "nix" => "nix",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+/// Like [`detect`], but falls back to the `#!` line for extensionless scripts.
+pub fn detect_with_shebang(path: &Path, content: &str) -> Option<&'static str> {
+    detect(path).or_else(|| {
+        let first = content.lines().next()?.strip_prefix("#!")?;
+        // `#!/usr/bin/env python3` and `#!/bin/bash` both name the interpreter last.
+        let interpreter = first.split_whitespace().find(|w| !w.ends_with("/env") && !w.starts_with('-'))?;
+        let interpreter = interpreter.rsplit('/').next()?;
+        let lang = match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+            "python" => "python",
+            "sh" | "bash" | "zsh" | "dash" | "ksh" => "bash",
+            "fish" => "fish",
+            "node" | "deno" | "bun" => "javascript",
+            "ruby" => "ruby",
+            "perl" => "perl",
+            "lua" => "lua",
+            "php" => "php",
+            _ => return None,
+        };
+        Some(lang)
+    })
+}
@@ -0,0 +1,188 @@
+use std::{collections::HashSet, fs, io::{self, Read}, path::{Path, PathBuf}};
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+
+use crate::{cli::Options, git, outline};
+
+/// A node of the project tree. `items` is kept in depth-first order, so the
+/// descendants of a directory always form a contiguous run right after it.
+#[derive(Clone)]
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+    /// `path` relative to the project root, used for glob matching and output.
+    pub rel: PathBuf,
+    pub depth: usize,
+    pub parent: Option<usize>,
+    pub is_dir: bool,
+    pub expanded: bool,
+    pub hidden: bool,
+    pub ignored: bool,
+    pub selected: bool,
+    /// Estimated token count, filled in lazily for rows that need it.
+    pub tokens: Option<usize>,
+    /// Uncommitted or `--since` changes, when the root is in a git repository.
+    pub git: Option<git::Change>,
+    /// How many reasons the secret scanner found to worry, once scanned.
+    pub secrets: Option<usize>,
+    /// Line ranges (1-based, inclusive, sorted, disjoint) to emit instead
+    /// of the whole file; empty means all of it.
+    pub ranges: Vec<(usize, usize)>,
+    /// Emit declarations only, with function bodies left out. Picking line
+    /// ranges takes precedence.
+    pub skeleton: bool,
+}
+
+/// How much of a file goes into the output when it isn't all of it.
+pub enum Part {
+    Ranges(Vec<(usize, usize)>),
+    Skeleton,
+}
+
+impl Entry {
+    pub fn part(&self) -> Option<Part> {
+        if !self.ranges.is_empty() { return Some(Part::Ranges(self.ranges.clone())); }
+        self.skeleton.then_some(Part::Skeleton)
+    }
+    /// Deselecting a file also forgets any line ranges picked in it.
+    pub fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+        if !selected && !self.ranges.is_empty() {
+            self.ranges.clear();
+            self.tokens = None;
+        }
+    }
+}
+
+/// The `--include`/`--exclude`/`--hidden`/`--no-ignore` knobs, compiled,
+/// plus the git changes to pick.
+pub struct Filter {
+    include: Option<GlobSet>,
+    skeleton: GlobSet,
+    git: git::Pick,
+    exclude: GlobSet,
+    hidden: bool,
+    no_ignore: bool,
+}
+
+impl Filter {
+    pub fn new(opts: &Options) -> Result<Self> {
+        let include = if opts.include.is_empty() { None } else { Some(build_globset(&opts.include)?) };
+        Ok(Self { include, skeleton: build_globset(&opts.skeleton)?, git: opts.git_pick, exclude: build_globset(&opts.exclude)?, hidden: opts.hidden, no_ignore: opts.no_ignore })
+    }
+    /// Whether anything should be selected before the user gets a say.
+    pub fn preselects(&self) -> bool {
+        self.include.is_some() || self.git.any()
+    }
+    /// Excluded paths never make it into the listing.
+    fn excludes(&self, rel: &Path) -> bool {
+        self.exclude.is_match(rel)
+    }
+    /// Whether a file should start out as a skeleton (`--skeleton`).
+    pub fn wants_skeleton(&self, entry: &Entry) -> bool {
+        !entry.is_dir && self.skeleton.is_match(&entry.rel) && outline::supports_file(&entry.path)
+    }
+    /// Whether a file should be picked without the user touching it.
+    pub fn wants(&self, entry: &Entry) -> bool {
+        !entry.is_dir
+            && (self.hidden || !entry.hidden)
+            && (self.no_ignore || !entry.ignored)
+            && self.include.as_ref().is_none_or(|set| set.is_match(&entry.rel))
+            && (!self.git.any() || entry.git.is_some_and(|c| self.git.matches(&c)))
+    }
+}
+
+fn build_globset(globs: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for g in globs {
+        builder.add(Glob::new(g)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Paths under `root` that survive the same rules as ripgrep: nested
+/// `.gitignore`s, `.git/info/exclude`, `core.excludesFile`, `.ignore`, and
+/// our own `.sharkitignore` on top. Anything not in the set is ignored.
+fn not_ignored(root: &Path) -> HashSet<PathBuf> {
+    WalkBuilder::new(root)
+        .hidden(false)
+        .add_custom_ignore_filename(".sharkitignore")
+        .filter_entry(|e| e.file_name() != ".git")
+        .build()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+pub fn list_files(root: &Path, filter: &Filter) -> io::Result<Vec<Entry>> {
+    let mut walker = Walker { root, kept: not_ignored(root), filter, out: Vec::new() };
+    walker.walk(root, None, 0, false)?;
+    Ok(walker.out)
+}
+
+struct Walker<'a> {
+    root: &'a Path,
+    kept: HashSet<PathBuf>,
+    filter: &'a Filter,
+    out: Vec<Entry>,
+}
+
+impl Walker<'_> {
+    fn walk(&mut self, dir: &Path, parent: Option<usize>, depth: usize, parent_hidden: bool) -> io::Result<()> {
+        let mut children = Vec::new();
+        for ent in fs::read_dir(dir)? {
+            let ent = ent?;
+            let path = ent.path();
+            let name = match path.file_name().and_then(|s| s.to_str()) { Some(s) => s.to_string(), None => continue };
+            if name == ".git" { continue; }
+            let is_dir = ent.file_type()?.is_dir();
+            if !is_dir && !path.is_file() { continue; }
+            let rel = path.strip_prefix(self.root).unwrap_or(&path).to_path_buf();
+            if self.filter.excludes(&rel) { continue; }
+            let hidden = parent_hidden || name.starts_with('.');
+            let ignored = !self.kept.contains(&path);
+            children.push(Entry { name, path, rel, depth, parent, is_dir, expanded: false, hidden, ignored, selected: false, tokens: None, git: None, secrets: None, ranges: Vec::new(), skeleton: false });
+        }
+        children.sort_by(|a, b| {
+            b.is_dir.cmp(&a.is_dir)
+                .then(a.hidden.cmp(&b.hidden))
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+        for child in children {
+            let (is_dir, hidden, path) = (child.is_dir, child.hidden, child.path.clone());
+            self.out.push(child);
+            if is_dir {
+                // Unreadable directories still show up, just without children.
+                let idx = self.out.len() - 1;
+                let _ = self.walk(&path, Some(idx), depth + 1, hidden);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a newline-separated list of paths from `source` (`-` for stdin) and
+/// turns each into a path relative to `root`. Relative paths are taken to be
+/// relative to `root` already; blank lines and `#` comments are skipped.
+pub fn read_file_list(root: &Path, source: &str) -> Result<Vec<String>> {
+    let text = if source == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).context("reading the file list from stdin")?;
+        text
+    } else {
+        fs::read_to_string(source).with_context(|| format!("reading the file list from {}", source))?
+    };
+    let canonical_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    Ok(text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).map(|line| {
+        let path = Path::new(line);
+        let rel = if path.is_absolute() {
+            let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            path.strip_prefix(&canonical_root).map(Path::to_path_buf).unwrap_or(path)
+        } else {
+            path.components().filter(|c| *c != std::path::Component::CurDir).collect()
+        };
+        rel.to_string_lossy().into_owned()
+    }).collect())
+}
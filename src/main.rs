@@ -1,220 +1,246 @@
-use std::{fs, io, path::PathBuf};
+use std::{fs::{File, OpenOptions}, io::{self, Write}};
 use anyhow::Result;
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{
-    prelude::*,
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
-};
-use ignore::gitignore::GitignoreBuilder;
+use ratatui::{prelude::*, widgets::ListState};
 
-#[derive(Clone)]
-struct Entry {
-    name: String,
-    path: PathBuf,
-    hidden: bool,
-    ignored: bool,
-    selected: bool,
-}
+mod app;
+mod bundle;
+mod cli;
+mod clipboard;
+mod config;
+mod content;
+mod fuzzy;
+mod git;
+mod highlight;
+mod lang;
+mod listing;
+mod outline;
+mod presets;
+mod redact;
+mod secrets;
+mod state;
+mod tokens;
+mod tree;
+mod ui;
 
-struct App {
-    items: Vec<Entry>,
-    cursor: usize,
-    preview_content: String,
-    show_preview: bool,
+use app::{App, Focus, Prompt};
+use cli::OutputMode;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Sink {
+    Stdout,
+    Clipboard,
 }
 
-impl App {
-    fn new(mut items: Vec<Entry>) -> Self {
-        items.sort_by(|a, b| {
-            match (a.hidden, b.hidden) {
-                (true, false) => std::cmp::Ordering::Greater,
-                (false, true) => std::cmp::Ordering::Less,
-                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-            }
-        });
-        let mut app = Self { items, cursor: 0, preview_content: String::new(), show_preview: true };
-        app.update_preview();
-        app
-    }
-    fn select_all(&mut self) {
-        for it in &mut self.items { it.selected = true; }
-    }
-    fn select_none(&mut self) {
-        for it in &mut self.items { it.selected = false; }
+fn main() -> Result<()> {
+    let opts = cli::Options::from_env()?;
+    let filter = listing::Filter::new(&opts)?;
+    let mut items = listing::list_files(&opts.root, &filter)?;
+    if !git::annotate(&opts.root, &mut items, opts.since.as_deref())? && opts.git_pick.any() {
+        anyhow::bail!("--modified/--staged/--untracked need {} to be inside a git repository", opts.root.display());
     }
-    fn select_only_n(&mut self, n: usize) {
-        self.select_none();
-        if !self.items.is_empty() {
-            let idx = n.min(self.items.len() - 1);
-            self.items[idx].selected = true;
-            self.cursor = idx;
+    if let Some(git::DiffBase::Ref(r)) = &opts.diff_base {
+        if !git::is_commit(&opts.root, r) {
+            anyhow::bail!("--diff-base: `{}` isn't a commit, branch or tag in this repository", r);
         }
     }
-    fn toggle_current(&mut self) {
-        if self.items.is_empty() { return; }
-        let it = &mut self.items[self.cursor];
-        it.selected = !it.selected;
-    }
-    fn move_up(&mut self) {
-        if self.items.is_empty() { return; }
-        if self.cursor == 0 { self.cursor = self.items.len() - 1; } else { self.cursor -= 1; }
-        self.update_preview();
-    }
-    fn move_down(&mut self) {
-        if self.items.is_empty() { return; }
-        self.cursor = (self.cursor + 1) % self.items.len();
-        self.update_preview();
-    }
-    fn selected_paths(&self) -> Vec<PathBuf> {
-        self.items.iter().filter(|e| e.selected).map(|e| e.path.clone()).collect()
-    }
-    fn selected_count(&self) -> usize {
-        self.items.iter().filter(|e| e.selected).count()
-    }
-    
-    fn update_preview(&mut self) {
-        if self.items.is_empty() {
-            self.preview_content = "No files available".to_string();
-            return;
+    let mut redactor = redact::Redactor::load(&opts.root)?;
+    redactor.secrets = opts.redact_secrets;
+    let mut app = App::new(items, &opts, &filter, redactor);
+
+    let (mode, sink) = if let Some(name) = &opts.preset {
+        let preset = presets::find(&opts.root, name)?;
+        let missing = app.apply_preset(&preset);
+        for m in &missing {
+            eprintln!("sharkit: preset `{}`: nothing matches {}", name, m);
+        }
+        (opts.output, Sink::Stdout)
+    } else if let Some(source) = &opts.files_from {
+        let paths = listing::read_file_list(&opts.root, source)?;
+        let missing = app.apply_preset(&presets::Preset { paths, ..Default::default() });
+        for m in &missing {
+            eprintln!("sharkit: {} isn't in {} (or is excluded)", m, opts.root.display());
+        }
+        (opts.output, Sink::Stdout)
+    } else if opts.non_interactive {
+        app.select_matching(&filter);
+        (opts.output, Sink::Stdout)
+    } else {
+        // Drawn on the controlling terminal so stdout stays free for the result.
+        let Ok(tty) = OpenOptions::new().read(true).write(true).open("/dev/tty") else {
+            anyhow::bail!(
+                "the picker needs a terminal, but there's no controlling terminal to draw it on\n\
+                 to run headless, pick files with one of:\n  \
+                 sharkit -n [-i GLOB]...       everything, or what the globs match\n  \
+                 sharkit --preset NAME         a saved preset\n  \
+                 sharkit --files-from -        paths listed on stdin"
+            );
+        };
+        if !filter.preselects() { app.restore_selection(); }
+        let Some(choice) = run_tui(&mut app, &opts, tty)? else { std::process::exit(130) };
+        if let Err(e) = state::save_selection(&opts.root, &app.selected_rels()) {
+            eprintln!("sharkit: warning: couldn't remember this selection: {:#}", e);
         }
-        
-        let current_file = &self.items[self.cursor].path;
-        self.preview_content = match fs::read_to_string(current_file) {
-            Ok(content) => {
-                if content.is_empty() {
-                    "<empty file>".to_string()
-                } else if content.len() > 10000 {
-                    format!("{}
+        choice
+    };
 
-... (truncated, file is {} bytes)", &content[..10000], content.len())
-                } else {
-                    content
-                }
+    if mode.carries_contents(opts.contents) && !app.allow_secrets && !app.redactor.secrets {
+        let flagged = app.flagged_selection();
+        if !flagged.is_empty() {
+            for (rel, reasons) in &flagged {
+                eprintln!("sharkit: {} may hold secrets: {}", rel, reasons.join(", "));
             }
-            Err(e) => format!("Error reading file: {}", e),
-        };
-    }
-    
-    fn toggle_preview(&mut self) {
-        self.show_preview = !self.show_preview;
+            anyhow::bail!("not emitting {} files that may hold secrets; pass --redact-secrets to mask them or --allow-secrets to emit anyway", flagged.len());
+        }
     }
-}
-
-fn build_ignore_matcher() -> ignore::gitignore::Gitignore {
-    let mut builder = GitignoreBuilder::new(".");
-    let _ = builder.add(".gitignore");
-    builder.build().unwrap_or_else(|_| ignore::gitignore::Gitignore::empty())
-}
 
-fn list_files() -> io::Result<Vec<Entry>> {
-    let gi = build_ignore_matcher();
-    let mut out = Vec::new();
-    for ent in fs::read_dir(".")? {
-        let ent = ent?;
-        let path = ent.path();
-        if !path.is_file() { continue; }
-        let name = match path.file_name().and_then(|s| s.to_str()) { Some(s) => s.to_string(), None => continue };
-        let hidden = name.starts_with('.');
-        let ignored = gi.matched_path_or_any_parents(&path, false).is_ignore();
-        out.push(Entry { name, path, hidden, ignored, selected: false });
+    app.count_tokens();
+    if app.over_budget() {
+        eprintln!("sharkit: warning: selection is ~{} tokens, over the budget of {}", app.selected_tokens(), opts.budget.unwrap_or(0));
     }
-    Ok(out)
-}
 
-fn draw(ui: &mut Frame, app: &App, list_state: &mut ListState) {
-    let main_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Min(1), Constraint::Length(5)].as_ref())
-        .split(ui.size());
-
-    let content_area = if app.show_preview {
-        Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
-            .split(main_chunks[0])
-            .to_vec()
-    } else {
-        vec![main_chunks[0]]
+    let mut paths = app.selected_paths();
+    if mode.is_bundle() {
+        let (text_files, binaries) = content::split_binaries(paths);
+        if !binaries.is_empty() {
+            eprintln!("sharkit: left {} binary files out of the bundle", binaries.len());
+        }
+        paths = text_files;
+    }
+    let tree = app.include_tree.then(|| {
+        let root = opts.root.canonicalize().unwrap_or_else(|_| opts.root.clone());
+        let name = root.file_name().map_or(".".into(), |n| n.to_string_lossy());
+        tree::render(&app.items, &paths, &name, opts.tree_limits)
+    });
+    let redact = &app.redactor;
+    let parts = app.selected_parts();
+    let text = match mode {
+        OutputMode::Paths => paths.iter().map(|p| format!("{}\n", bundle::rel_path(p, &opts.root).display())).collect(),
+        OutputMode::Markdown => bundle::render_markdown(&paths, &opts.root, &parts, tree.as_deref(), redact),
+        OutputMode::Xml => bundle::render_xml(&paths, &opts.root, opts.xml_attrs, &parts, tree.as_deref(), redact),
+        OutputMode::Json => bundle::render_json(&app.selected_entries(), opts.contents, false, redact),
+        OutputMode::JsonLines => bundle::render_json(&app.selected_entries(), opts.contents, true, redact),
+        OutputMode::Diff | OutputMode::Hybrid => {
+            let base = opts.diff_base.as_ref().expect("set by Options::parse");
+            let (text, unchanged) = if mode == OutputMode::Diff {
+                bundle::render_diff(&paths, &opts.root, base, opts.diff_context, redact)?
+            } else {
+                bundle::render_hybrid(&paths, &opts.root, base, opts.diff_context, tree.as_deref(), redact)?
+            };
+            if unchanged > 0 {
+                eprintln!("sharkit: left out {} selected files with no changes against {}", unchanged, base);
+            }
+            text
+        }
     };
-
-    let items: Vec<ListItem> = app.items.iter().enumerate().map(|(_i, e)| {
-        let mark = if e.selected { "✓" } else { " " };
-        let line = format!(" [{}] {}", mark, e.name);
-        let style = if e.hidden || e.ignored {
-            Style::default().fg(Color::Gray).add_modifier(Modifier::DIM)
-        } else {
-            Style::default().fg(Color::White)
-        };
-        ListItem::new(line).style(style)
-    }).collect();
-
-    let list = List::new(items)
-        .block(Block::default().title("sharkit").borders(Borders::ALL))
-        .highlight_style(Style::default().bg(Color::Cyan).fg(Color::Black))
-        .highlight_symbol("› ");
-
-    ui.render_stateful_widget(list, content_area[0], list_state);
-
-    if app.show_preview && content_area.len() > 1 {
-        let preview_title = if app.items.is_empty() {
-            "Preview".to_string()
-        } else {
-            format!("Preview: {}", app.items[app.cursor].name)
-        };
-
-        let preview = Paragraph::new(app.preview_content.as_str())
-            .block(Block::default().title(preview_title).borders(Borders::ALL))
-            .wrap(Wrap { trim: false })
-            .scroll((0, 0));
-
-        ui.render_widget(preview, content_area[1]);
+    match sink {
+        Sink::Stdout => match io::stdout().write_all(text.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        },
+        Sink::Clipboard => match clipboard::copy(&text) {
+            Ok(method) => eprintln!("sharkit: copied {} bytes from {} files via {}", text.len(), paths.len(), method),
+            Err(e) => {
+                eprintln!("sharkit: copy failed: {}", e);
+                std::process::exit(1);
+            }
+        },
     }
-
-    let help_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
-        .split(main_chunks[1]);
-
-    let navigation_help = Paragraph::new("Navigation:\n[↑/↓ or j/k] move cursor\n[space] toggle selection\n[enter] confirm  [q/esc] quit")
-        .block(Block::default().title("Controls").borders(Borders::ALL))
-        .wrap(Wrap { trim: false });
-    ui.render_widget(navigation_help, help_chunks[0]);
-
-    let selection_help = Paragraph::new(format!("Selection:\n[a/A] select all\n[n] select none\n[p] toggle preview\n\n{} selected", app.selected_count()))
-        .block(Block::default().title("Actions").borders(Borders::ALL))
-        .wrap(Wrap { trim: false });
-    ui.render_widget(selection_help, help_chunks[1]);
+    let redactions = app.redactor.counts();
+    if !redactions.is_empty() {
+        let total: usize = redactions.iter().map(|(_, n)| n).sum();
+        let per_file: Vec<String> = redactions.iter().map(|(f, n)| format!("{} ({})", f, n)).collect();
+        eprintln!("sharkit: redacted {} spans: {}", total, per_file.join(", "));
+    }
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let items = list_files()?;
-    let mut app = App::new(items);
-
+/// Runs the picker until the user confirms (returning how to emit the
+/// selection) or quits (returning `None`).
+fn run_tui(app: &mut App, opts: &cli::Options, mut tty: File) -> Result<Option<(OutputMode, Sink)>> {
+    let output = opts.output;
     enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = ratatui::backend::CrosstermBackend::new(stdout);
+    execute!(tty, EnterAlternateScreen)?;
+    let backend = ratatui::backend::CrosstermBackend::new(tty);
     let mut terminal = Terminal::new(backend)?;
     let mut list_state = ListState::default();
-    if !app.items.is_empty() { list_state.select(Some(0)); }
+    // `b` and `c` always emit contents, in the requested format if it has them.
+    let bundle_mode = if output.is_bundle() { output } else { OutputMode::Markdown };
 
-    let mut confirmed = false;
-    loop {
-        terminal.draw(|f| draw(f, &app, &mut list_state))?;
+    let choice = loop {
+        app.count_tokens();
+        app.scan_secrets();
+        list_state.select(app.current_index().map(|_| app.cursor));
+        terminal.draw(|f| ui::draw(f, app, &mut list_state))?;
 
         if let Event::Key(KeyEvent { code, modifiers, .. }) = event::read()? {
+            if app.prompt.is_some() {
+                match handle_prompt_key(app, code) {
+                    Some(choice) => break Some(choice),
+                    None => continue,
+                }
+            }
+            if app.filtering {
+                match code {
+                    KeyCode::Char(c) => app.push_query(c),
+                    KeyCode::Backspace => app.pop_query(),
+                    KeyCode::Esc => app.clear_filter(),
+                    KeyCode::Enter => app.finish_filter(),
+                    KeyCode::Up => { app.finish_filter(); app.move_up(); }
+                    KeyCode::Down => { app.finish_filter(); app.move_down(); }
+                    _ => {}
+                }
+                continue;
+            }
+            if app.focus == Focus::Preview {
+                match code {
+                    KeyCode::Up | KeyCode::Char('k') if app.outline_view => { app.move_outline_cursor(-1); continue; }
+                    KeyCode::Down | KeyCode::Char('j') if app.outline_view => { app.move_outline_cursor(1); continue; }
+                    KeyCode::PageUp if app.outline_view => { app.move_outline_cursor(-(app.preview_height.get() as isize)); continue; }
+                    KeyCode::PageDown if app.outline_view => { app.move_outline_cursor(app.preview_height.get() as isize); continue; }
+                    KeyCode::Char('g') | KeyCode::Home if app.outline_view => { app.move_outline_cursor(isize::MIN / 2); continue; }
+                    KeyCode::Char('G') | KeyCode::End if app.outline_view => { app.move_outline_cursor(isize::MAX / 2); continue; }
+                    KeyCode::Char(' ') | KeyCode::Char('y') if app.outline_view => { app.toggle_symbol(); continue; }
+                    KeyCode::Up | KeyCode::Char('k') => { app.move_preview_cursor(-1); continue; }
+                    KeyCode::Down | KeyCode::Char('j') => { app.move_preview_cursor(1); continue; }
+                    KeyCode::Left | KeyCode::Char('h') => { app.scroll_preview_sideways(-8); continue; }
+                    KeyCode::Right | KeyCode::Char('l') => { app.scroll_preview_sideways(8); continue; }
+                    KeyCode::PageUp => { app.page_preview(-1); continue; }
+                    KeyCode::PageDown | KeyCode::Char(' ') => { app.page_preview(1); continue; }
+                    KeyCode::Char('g') | KeyCode::Home => { app.preview_top(); continue; }
+                    KeyCode::Char('G') | KeyCode::End => { app.preview_bottom(); continue; }
+                    KeyCode::Char('v') => { app.toggle_visual(); continue; }
+                    KeyCode::Char('y') => { app.add_preview_range(); continue; }
+                    KeyCode::Char('d') => { app.clear_preview_ranges(); continue; }
+                    KeyCode::Esc if app.visual_anchor.is_some() => { app.visual_anchor = None; continue; }
+                    KeyCode::Esc => { app.toggle_focus(); continue; }
+                    _ => {}
+                }
+            }
             match (code, modifiers) {
-                (KeyCode::Up, _) | (KeyCode::Char('k'), _) => { app.move_up(); list_state.select(Some(app.cursor)); }
-                (KeyCode::Down, _) | (KeyCode::Char('j'), _) => { app.move_down(); list_state.select(Some(app.cursor)); }
+                (KeyCode::Tab, _) => app.toggle_focus(),
+                (KeyCode::PageUp, _) => app.page_preview(-1),
+                (KeyCode::PageDown, _) => app.page_preview(1),
+                (KeyCode::Up, _) | (KeyCode::Char('k'), _) => app.move_up(),
+                (KeyCode::Down, _) | (KeyCode::Char('j'), _) => app.move_down(),
+                (KeyCode::Right, _) | (KeyCode::Char('l'), _) => app.expand_current(),
+                (KeyCode::Left, _) | (KeyCode::Char('h'), _) => app.collapse_current(),
                 (KeyCode::Char(' '), _) => app.toggle_current(),
-                (KeyCode::Char('a'), _) | (KeyCode::Char('A'), _) => app.select_all(),
+                (KeyCode::Char('a'), _) => app.select_all(),
+                (KeyCode::Char('A'), _) => app.select_all_with_hidden(),
+                (KeyCode::Char('i'), _) => app.select_only_ignored(),
+                (KeyCode::Char('*'), _) => app.invert_selection(),
                 (KeyCode::Char('n'), _) => app.select_none(),
-                (KeyCode::Enter, _) => { confirmed = true; break; }
-                (KeyCode::Esc, _) | (KeyCode::Char('q'), _) => { confirmed = false; break; }
+                (KeyCode::Enter, _) => if let Some(choice) = app.request_emit(output, Sink::Stdout, opts.contents) { break Some(choice) },
+                (KeyCode::Char('b'), _) => if let Some(choice) = app.request_emit(bundle_mode, Sink::Stdout, opts.contents) { break Some(choice) },
+                (KeyCode::Char('c'), _) => if let Some(choice) = app.request_emit(bundle_mode, Sink::Clipboard, opts.contents) { break Some(choice) },
+                (KeyCode::Char('/'), _) => app.start_filter(),
+                (KeyCode::Esc, _) if !app.query.is_empty() => app.clear_filter(),
+                (KeyCode::Esc, _) | (KeyCode::Char('q'), _) => break None,
                 (KeyCode::Char('1'), KeyModifiers::SHIFT) => app.select_only_n(0),
                 (KeyCode::Char('2'), KeyModifiers::SHIFT) => app.select_only_n(1),
                 (KeyCode::Char('3'), KeyModifiers::SHIFT) => app.select_only_n(2),
@@ -224,26 +250,65 @@ fn main() -> Result<()> {
                 (KeyCode::Char('7'), KeyModifiers::SHIFT) => app.select_only_n(6),
                 (KeyCode::Char('8'), KeyModifiers::SHIFT) => app.select_only_n(7),
                 (KeyCode::Char('9'), KeyModifiers::SHIFT) => app.select_only_n(8),
-                (KeyCode::Char('0'), KeyModifiers::SHIFT) => {
-                    if !app.items.is_empty() { app.select_only_n(app.items.len() - 1); }
-                }
+                (KeyCode::Char('0'), KeyModifiers::SHIFT) if !app.visible.is_empty() => app.select_only_n(app.visible.len() - 1),
                 (KeyCode::Char('p'), _) => app.toggle_preview(),
+                (KeyCode::Char('O'), _) => app.toggle_outline(),
+                (KeyCode::Char('K'), _) => app.toggle_skeleton(),
+                (KeyCode::Char('t'), _) => app.toggle_tree(),
+                (KeyCode::Char('s'), _) => app.start_save_preset(),
+                (KeyCode::Char('o'), _) => app.start_load_preset(),
+                (KeyCode::Char('x'), _) => app.forget_selection(),
+                (KeyCode::Char('m'), _) => app.select_changed(git::Pick { modified: true, ..Default::default() }),
+                (KeyCode::Char('S'), _) => app.select_changed(git::Pick { staged: true, ..Default::default() }),
+                (KeyCode::Char('u'), _) => app.select_changed(git::Pick { untracked: true, ..Default::default() }),
+                (KeyCode::Char('M'), _) => app.select_changed(git::Pick { modified: true, staged: true, untracked: true, since: true }),
                 _ => {}
             }
         }
-    }
+    };
 
     disable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, LeaveAlternateScreen)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    Ok(choice)
+}
 
-    if confirmed {
-        let sel = app.selected_paths();
-        for p in sel {
-            println!("{}", pathdiff::diff_paths(&p, ".").unwrap_or(p).display());
-        }
-        std::process::exit(0);
-    } else {
-        std::process::exit(130);
+/// Feeds a key to the open prompt; returns how to emit if the prompt ends in
+/// output going ahead.
+fn handle_prompt_key(app: &mut App, code: KeyCode) -> Option<(OutputMode, Sink)> {
+    match app.prompt.take() {
+        Some(Prompt::SavePreset(mut name)) => match code {
+            KeyCode::Enter => app.save_preset(&name),
+            KeyCode::Esc => {}
+            KeyCode::Backspace => { name.pop(); app.prompt = Some(Prompt::SavePreset(name)); }
+            KeyCode::Char(c) => { name.push(c); app.prompt = Some(Prompt::SavePreset(name)); }
+            _ => app.prompt = Some(Prompt::SavePreset(name)),
+        },
+        Some(Prompt::LoadPreset { presets, mut cursor }) => match code {
+            KeyCode::Enter => app.load_preset(&presets[cursor]),
+            KeyCode::Esc | KeyCode::Char('q') => {}
+            KeyCode::Up | KeyCode::Char('k') => {
+                cursor = cursor.checked_sub(1).unwrap_or(presets.len() - 1);
+                app.prompt = Some(Prompt::LoadPreset { presets, cursor });
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                cursor = (cursor + 1) % presets.len();
+                app.prompt = Some(Prompt::LoadPreset { presets, cursor });
+            }
+            _ => app.prompt = Some(Prompt::LoadPreset { presets, cursor }),
+        },
+        Some(Prompt::Secrets { mode, sink, files }) => match code {
+            KeyCode::Char('r') => {
+                app.redactor.secrets = true;
+                return Some((mode, sink));
+            }
+            KeyCode::Char('y') => {
+                app.allow_secrets = true;
+                return Some((mode, sink));
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {}
+            _ => app.prompt = Some(Prompt::Secrets { mode, sink, files }),
+        },
+        None => {}
     }
+    None
 }
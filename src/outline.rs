@@ -0,0 +1,576 @@
+use std::{collections::HashMap, ops::Range, path::Path, sync::OnceLock};
+
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor, StreamingIterator};
+
+use crate::lang;
+
+/// A declaration in a file: what it is, its name, how deeply it's nested,
+/// and the lines it spans (1-based, inclusive, counting the doc comments and
+/// attributes right above it).
+pub struct Symbol {
+    pub kind: &'static str,
+    pub name: String,
+    pub depth: usize,
+    pub start: usize,
+    pub end: usize,
+    /// Byte range of a function's body, the part a skeleton leaves out.
+    pub body: Option<Range<usize>>,
+}
+
+/// Kinds whose bodies a skeleton leaves out.
+const FUNCTIONS: [&str; 5] = ["fn", "func", "function", "method", "def"];
+
+/// Each pattern captures the declaration under its kind and, where the
+/// grammar has one, its `@name`; the rest of the name is worked out in
+/// `name_of`.
+const RUST: &str = r#"
+(function_item name: (identifier) @name) @fn
+(function_signature_item name: (identifier) @name) @fn
+(struct_item name: (type_identifier) @name) @struct
+(enum_item name: (type_identifier) @name) @enum
+(union_item name: (type_identifier) @name) @union
+(trait_item name: (type_identifier) @name) @trait
+(impl_item) @impl
+(mod_item name: (identifier) @name) @mod
+(type_item name: (type_identifier) @name) @type
+(const_item name: (identifier) @name) @const
+(static_item name: (identifier) @name) @static
+(macro_definition name: (identifier) @name) @macro
+"#;
+
+const PYTHON: &str = r#"
+(function_definition name: (identifier) @name) @def
+(class_definition name: (identifier) @name) @class
+"#;
+
+const TYPESCRIPT: &str = r#"
+(function_declaration name: (identifier) @name) @function
+(generator_function_declaration name: (identifier) @name) @function
+(lexical_declaration (variable_declarator name: (identifier) @name value: [(arrow_function) (function_expression)])) @function
+(variable_declaration (variable_declarator name: (identifier) @name value: [(arrow_function) (function_expression)])) @function
+(class_declaration name: (type_identifier) @name) @class
+(abstract_class_declaration name: (type_identifier) @name) @class
+(interface_declaration name: (type_identifier) @name) @interface
+(enum_declaration name: (identifier) @name) @enum
+(type_alias_declaration name: (type_identifier) @name) @type
+(internal_module name: (_) @name) @namespace
+(method_definition name: (_) @name) @method
+(method_signature name: (_) @name) @method
+(abstract_method_signature name: (_) @name) @method
+(public_field_definition name: (_) @name value: [(arrow_function) (function_expression)]) @method
+"#;
+
+const GO: &str = r#"
+(function_declaration name: (identifier) @name) @func
+(method_declaration name: (field_identifier) @name) @func
+(type_spec name: (type_identifier) @name) @type
+(type_alias name: (type_identifier) @name) @type
+"#;
+
+const C: &str = r#"
+(function_definition) @function
+(struct_specifier name: (type_identifier) @name body: (_)) @struct
+(union_specifier name: (type_identifier) @name body: (_)) @union
+(enum_specifier name: (type_identifier) @name body: (_)) @enum
+(type_definition declarator: (type_identifier) @name) @typedef
+"#;
+
+const CPP: &str = r#"
+(function_definition) @function
+(field_declaration declarator: (function_declarator)) @function
+(class_specifier name: (type_identifier) @name body: (_)) @class
+(struct_specifier name: (type_identifier) @name body: (_)) @struct
+(union_specifier name: (type_identifier) @name body: (_)) @union
+(enum_specifier name: (type_identifier) @name body: (_)) @enum
+(namespace_definition) @namespace
+(type_definition declarator: (type_identifier) @name) @typedef
+(alias_declaration name: (type_identifier) @name) @type
+"#;
+
+struct Grammar {
+    language: Language,
+    query: Query,
+    /// Kinds whose members are symbols too; anything declared inside other
+    /// symbols, like a struct in a function body, is left out.
+    containers: &'static [&'static str],
+}
+
+fn grammar(lang: &str) -> Option<&'static Grammar> {
+    static CACHE: [OnceLock<Grammar>; 7] = [const { OnceLock::new() }; 7];
+    let (slot, language, query, containers): (usize, Language, &str, &'static [&'static str]) = match lang {
+        "rust" => (0, tree_sitter_rust::LANGUAGE.into(), RUST, &["impl", "trait", "mod"]),
+        "python" => (1, tree_sitter_python::LANGUAGE.into(), PYTHON, &["class"]),
+        "typescript" => (2, tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(), TYPESCRIPT, &["class", "interface", "namespace"]),
+        // The TSX grammar reads plain JavaScript and JSX as well.
+        "javascript" | "jsx" | "tsx" => (3, tree_sitter_typescript::LANGUAGE_TSX.into(), TYPESCRIPT, &["class", "interface", "namespace"]),
+        "go" => (4, tree_sitter_go::LANGUAGE.into(), GO, &[]),
+        "c" => (5, tree_sitter_c::LANGUAGE.into(), C, &[]),
+        "cpp" => (6, tree_sitter_cpp::LANGUAGE.into(), CPP, &["class", "struct", "namespace"]),
+        _ => return None,
+    };
+    Some(CACHE[slot].get_or_init(|| {
+        let query = Query::new(&language, query).expect("outline queries match the grammar");
+        Grammar { language, query, containers }
+    }))
+}
+
+pub fn supports(lang: &str) -> bool {
+    matches!(lang, "rust" | "python" | "javascript" | "jsx" | "typescript" | "tsx" | "go" | "c" | "cpp")
+}
+
+/// Whether `path`, going by its name, is in a language `outline` knows.
+pub fn supports_file(path: &Path) -> bool {
+    lang::detect(path).is_some_and(supports)
+}
+
+/// The functions, types, impls and classes in `text`, in file order, read
+/// off its syntax tree.
+pub fn outline(lang: &str, text: &str) -> Vec<Symbol> {
+    let Some(grammar) = grammar(lang) else { return Vec::new() };
+    let mut parser = Parser::new();
+    if parser.set_language(&grammar.language).is_err() { return Vec::new(); }
+    let Some(tree) = parser.parse(text, None) else { return Vec::new() };
+
+    let mut found: Vec<(Node, &'static str, Option<Node>)> = Vec::new();
+    let names = grammar.query.capture_names();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&grammar.query, tree.root_node(), text.as_bytes());
+    while let Some(m) = matches.next() {
+        let name = m.captures().iter().find(|c| names[c.index as usize] == "name").map(|c| c.node);
+        if let Some(c) = m.captures().iter().find(|c| names[c.index as usize] != "name") {
+            found.push((c.node, names[c.index as usize], name));
+        }
+    }
+    found.sort_by_key(|(node, _, _)| (node.start_byte(), std::cmp::Reverse(node.end_byte())));
+    found.dedup_by_key(|(node, _, _)| node.id());
+
+    // For every declaration seen: its depth and whether it's a container, or
+    // `None` when it was left out, and with it everything inside.
+    let mut seen: HashMap<usize, Option<(usize, bool)>> = HashMap::new();
+    let mut symbols = Vec::new();
+    for (node, kind, name) in found {
+        let kind = refine_kind(node, kind);
+        let enclosing = std::iter::successors(node.parent(), |n| n.parent()).find_map(|p| seen.get(&p.id()).copied());
+        let depth = match enclosing {
+            None => Some(0),
+            Some(Some((d, true))) => Some(d + 1),
+            Some(_) => None,
+        };
+        seen.insert(node.id(), depth.map(|d| (d, grammar.containers.contains(&kind))));
+        let Some(depth) = depth else { continue };
+        let span = span_of(node);
+        symbols.push(Symbol {
+            kind,
+            name: name_of(node, name, text),
+            depth,
+            start: docs_above(span).start_position().row + 1,
+            end: last_row(span) + 1,
+            body: if FUNCTIONS.contains(&kind) { body_of(lang, node) } else { None },
+        });
+    }
+    symbols
+}
+
+/// `text` with the body of every function replaced by `...`, keeping
+/// signatures, type definitions, doc comments and everything outside
+/// functions. Python keeps docstrings. `None` for languages `outline`
+/// doesn't know.
+pub fn skeleton(lang: &str, text: &str) -> Option<String> {
+    if !supports(lang) { return None; }
+    let mut out = String::with_capacity(text.len() / 2);
+    let mut next = 0;
+    for body in outline(lang, text).into_iter().filter_map(|s| s.body) {
+        // Methods sit inside class bodies, which are kept, but a function
+        // inside a cut body is already gone.
+        if body.start < next { continue; }
+        out.push_str(&text[next..body.start]);
+        out.push_str(if lang == "python" { "..." } else { "{ ... }" });
+        next = body.end;
+    }
+    out.push_str(&text[next..]);
+    Some(out)
+}
+
+/// Go's `type` covers structs and interfaces alike.
+fn refine_kind(node: Node, kind: &'static str) -> &'static str {
+    match node.child_by_field_name("type").map(|t| t.kind()) {
+        Some("struct_type") if node.kind() == "type_spec" => "struct",
+        Some("interface_type") if node.kind() == "type_spec" => "interface",
+        _ => kind,
+    }
+}
+
+/// The node whose lines a symbol covers: the declaration itself, or what
+/// wraps it with decorators, `export` or a `template<...>` header.
+fn span_of(node: Node) -> Node {
+    match node.parent() {
+        Some(p) if matches!(p.kind(), "decorated_definition" | "export_statement" | "template_declaration") => p,
+        // `type Name struct { ... }` rather than one spec of a `type ( ... )` group.
+        Some(p) if p.kind() == "type_declaration" && p.named_child_count() == 1 => p,
+        _ => node,
+    }
+}
+
+/// The first of the doc comments, attributes and decorators directly above
+/// `node`, or `node` itself. Comments trailing code on their line don't count.
+fn docs_above(node: Node) -> Node {
+    let mut first = node;
+    while let Some(prev) = first.prev_sibling() {
+        let doc = prev.kind().contains("comment") || matches!(prev.kind(), "attribute_item" | "decorator");
+        let adjacent = last_row(prev) + 1 >= first.start_position().row;
+        let own_line = prev.prev_sibling().is_none_or(|pp| last_row(pp) < prev.start_position().row);
+        if !(doc && adjacent && own_line) { break; }
+        first = prev;
+    }
+    first
+}
+
+/// 0-based line `node` ends on; some nodes, like Rust line comments, take
+/// in the newline that ends them.
+fn last_row(node: Node) -> usize {
+    let end = node.end_position();
+    if end.column == 0 && end.row > node.start_position().row { end.row - 1 } else { end.row }
+}
+
+fn name_of(node: Node, name: Option<Node>, text: &str) -> String {
+    let src = |n: Node| text[n.byte_range()].split_whitespace().collect::<Vec<_>>().join(" ");
+    match (node.kind(), name) {
+        ("impl_item", _) => {
+            let ty = node.child_by_field_name("type").map(src).unwrap_or_default();
+            match node.child_by_field_name("trait") {
+                Some(t) => format!("{} for {}", src(t), ty),
+                None => ty,
+            }
+        }
+        // `func (s *Server[T]) Run()` is `Server.Run`.
+        ("method_declaration", Some(name)) => {
+            let receiver = node.child_by_field_name("receiver").and_then(|r| r.named_child(0)).and_then(|p| p.child_by_field_name("type"));
+            let owner = receiver.map(src).unwrap_or_default();
+            let owner = owner.trim_start_matches('*');
+            format!("{}.{}", owner.split('[').next().unwrap_or(owner), src(name))
+        }
+        ("namespace_definition", _) => node.child_by_field_name("name").map_or("(anonymous)".to_string(), src),
+        // C and C++ functions are named by the innermost declarator, past
+        // pointers and parameter lists.
+        (_, None) => {
+            let mut declarator = node.child_by_field_name("declarator");
+            while let Some(inner) = declarator.and_then(|d| d.child_by_field_name("declarator")) { declarator = Some(inner); }
+            declarator.map(src).unwrap_or_default()
+        }
+        (_, Some(name)) => src(name),
+    }
+}
+
+/// What a skeleton cuts from a function: the braced body, or in Python the
+/// statements after the docstring.
+fn body_of(lang: &str, node: Node) -> Option<Range<usize>> {
+    fn value(n: Node) -> Option<Node> { n.child_by_field_name("value") }
+    // `const f = () => { ... }` keeps its body on the function it holds.
+    let body = node.child_by_field_name("body")
+        .or_else(|| value(node).and_then(|v| v.child_by_field_name("body")))
+        .or_else(|| node.named_child(0).and_then(value).and_then(|v| v.child_by_field_name("body")))?;
+    if !matches!(body.kind(), "block" | "statement_block" | "compound_statement") { return None; }
+    if lang != "python" { return Some(body.byte_range()); }
+    let first = body.named_child(0)?;
+    let docstring = first.kind() == "expression_statement" && first.named_child(0).is_some_and(|s| s.kind() == "string");
+    let start = if docstring { first.next_named_sibling()? } else { first };
+    Some(start.start_byte()..body.end_byte())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(depth, kind, name, start, end)` for every symbol.
+    fn symbols(lang: &str, text: &str) -> Vec<(usize, &'static str, String, usize, usize)> {
+        outline(lang, text).into_iter().map(|s| (s.depth, s.kind, s.name, s.start, s.end)).collect()
+    }
+
+    fn sym(depth: usize, kind: &'static str, name: &str, start: usize, end: usize) -> (usize, &'static str, String, usize, usize) {
+        (depth, kind, name.to_string(), start, end)
+    }
+
+    const RUST_SRC: &str = r##"use std::fmt;
+
+/// A point.
+#[derive(Debug)]
+pub struct Point<T> {
+    x: T,
+}
+
+impl<T> fmt::Display for Point<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{ {} }}", self.x)
+    }
+}
+
+pub fn head() -> &'static str {
+    r#"a " { "#
+}
+
+/// Keeps its doc.
+pub fn tail<T>(
+    a: T,
+    b: usize,
+) -> Vec<T>
+where
+    T: Clone,
+{
+    let brace = '}';
+    vec![a; b]
+}
+
+trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> String { String::new() }
+}
+
+mod inner {
+    pub const LIMIT: usize = 3; // trailing
+    fn helper() {
+        struct Local;
+    }
+}
+"##;
+
+    #[test]
+    fn rust_outline() {
+        assert_eq!(symbols("rust", RUST_SRC), [
+            sym(0, "struct", "Point", 3, 7),
+            sym(0, "impl", "fmt::Display for Point<T>", 9, 16),
+            sym(1, "fn", "fmt", 13, 15),
+            sym(0, "fn", "head", 18, 20),
+            sym(0, "fn", "tail", 22, 32),
+            sym(0, "trait", "Shape", 34, 37),
+            sym(1, "fn", "area", 35, 35),
+            sym(1, "fn", "name", 36, 36),
+            sym(0, "mod", "inner", 39, 44),
+            sym(1, "const", "LIMIT", 40, 40),
+            sym(1, "fn", "helper", 41, 43),
+        ]);
+    }
+
+    #[test]
+    fn rust_skeleton_survives_raw_strings() {
+        let out = skeleton("rust", RUST_SRC).unwrap();
+        assert!(out.contains("pub fn head() -> &'static str { ... }\n"), "{}", out);
+        assert!(out.contains("where\n    T: Clone,\n{ ... }\n"), "{}", out);
+        assert!(out.contains("    fn area(&self) -> f64;\n    fn name(&self) -> String { ... }\n"), "{}", out);
+        assert!(out.contains("/// Keeps its doc.\npub fn tail<T>(\n"), "{}", out);
+        for gone in ["r#\"a", "vec![a; b]", "write!", "struct Local"] {
+            assert!(!out.contains(gone), "{} left in:\n{}", gone, out);
+        }
+    }
+
+    const PYTHON_SRC: &str = r#"import functools
+
+# A helper.
+def plain(a, b):
+    return a + b
+
+
+@functools.cache
+@other
+def cached(
+    x: int,
+    y: int = 2,
+) -> int:
+    """Docstring stays."""
+    z = x * y
+    return z
+
+
+class Shape(Base):
+    """A shape."""
+
+    size = 3
+
+    @property
+    def area(self):
+        def inner():
+            return 1
+        return inner()
+
+    async def load(self): return await thing()
+"#;
+
+    #[test]
+    fn python_outline() {
+        assert_eq!(symbols("python", PYTHON_SRC), [
+            sym(0, "def", "plain", 3, 5),
+            sym(0, "def", "cached", 8, 16),
+            sym(0, "class", "Shape", 19, 30),
+            sym(1, "def", "area", 24, 28),
+            sym(1, "def", "load", 30, 30),
+        ]);
+    }
+
+    #[test]
+    fn python_skeleton_keeps_decorators_and_docstrings() {
+        let out = skeleton("python", PYTHON_SRC).unwrap();
+        assert!(out.contains("@functools.cache\n@other\ndef cached(\n    x: int,\n    y: int = 2,\n) -> int:\n    \"\"\"Docstring stays.\"\"\"\n    ...\n"), "{}", out);
+        assert!(out.contains("    @property\n    def area(self):\n        ...\n"), "{}", out);
+        assert!(out.contains("    async def load(self): ...\n"), "{}", out);
+        assert!(out.contains("    size = 3\n"), "{}", out);
+        for gone in ["z = x * y", "def inner", "await thing"] {
+            assert!(!out.contains(gone), "{} left in:\n{}", gone, out);
+        }
+    }
+
+    const TS_SRC: &str = r#"/** Adds. */
+export function add(a: number, b: number): number {
+  return a + b;
+}
+
+export const mul = (a: number, b: number) => {
+  return a * b;
+};
+
+@Component({ selector: "app" })
+export class Widget<T> extends Base {
+  private count = 0;
+  handler = () => { this.count++; };
+
+  @Input()
+  get value(): T {
+    return this.v;
+  }
+
+  async render(
+    el: HTMLElement,
+  ): Promise<void> {
+    const s = "}";
+  }
+}
+
+export interface Shape {
+  area(): number;
+}
+
+type Id = string | number;
+"#;
+
+    #[test]
+    fn typescript_outline() {
+        assert_eq!(symbols("typescript", TS_SRC), [
+            sym(0, "function", "add", 1, 4),
+            sym(0, "function", "mul", 6, 8),
+            sym(0, "class", "Widget", 10, 25),
+            sym(1, "method", "handler", 13, 13),
+            sym(1, "method", "value", 15, 18),
+            sym(1, "method", "render", 20, 24),
+            sym(0, "interface", "Shape", 27, 29),
+            sym(1, "method", "area", 28, 28),
+            sym(0, "type", "Id", 31, 31),
+        ]);
+    }
+
+    #[test]
+    fn typescript_skeleton() {
+        let out = skeleton("tsx", TS_SRC).unwrap();
+        assert!(out.contains("export const mul = (a: number, b: number) => { ... };\n"), "{}", out);
+        assert!(out.contains("  async render(\n    el: HTMLElement,\n  ): Promise<void> { ... }\n}\n"), "{}", out);
+        assert!(out.contains("  private count = 0;\n"), "{}", out);
+        assert!(!out.contains("return a + b") && !out.contains("const s"), "{}", out);
+    }
+
+    const GO_SRC: &str = r#"package main
+
+// Server serves.
+type Server struct {
+	addr string
+}
+
+type Handler interface {
+	Serve() error
+}
+
+// Run runs.
+func (s *Server) Run(
+	ctx context.Context,
+) error {
+	fmt.Println("}")
+	return nil
+}
+
+func (c Cache[K, V]) Get(k K) V { return c.m[k] }
+
+func main() {
+	type local struct{}
+}
+"#;
+
+    #[test]
+    fn go_outline_names_methods_by_receiver() {
+        assert_eq!(symbols("go", GO_SRC), [
+            sym(0, "struct", "Server", 3, 6),
+            sym(0, "interface", "Handler", 8, 10),
+            sym(0, "func", "Server.Run", 12, 18),
+            sym(0, "func", "Cache.Get", 20, 20),
+            sym(0, "func", "main", 22, 24),
+        ]);
+        let out = skeleton("go", GO_SRC).unwrap();
+        assert!(out.contains("func (s *Server) Run(\n\tctx context.Context,\n) error { ... }\n"), "{}", out);
+        assert!(out.contains("func (c Cache[K, V]) Get(k K) V { ... }\n"), "{}", out);
+        assert!(out.contains("type Server struct {\n\taddr string\n}\n"), "{}", out);
+    }
+
+    const C_SRC: &str = r#"#include <stdio.h>
+
+/* A node. */
+struct node {
+    int value;
+};
+
+typedef struct {
+    int x, y;
+} Point;
+
+int add(int a, int b);
+
+static int
+add(int a,
+    int b)
+{
+    return a + b; /* } */
+}
+"#;
+
+    #[test]
+    fn c_outline_skips_prototypes() {
+        assert_eq!(symbols("c", C_SRC), [
+            sym(0, "struct", "node", 3, 6),
+            sym(0, "typedef", "Point", 8, 10),
+            sym(0, "function", "add", 14, 19),
+        ]);
+        let out = skeleton("c", C_SRC).unwrap();
+        assert!(out.contains("int add(int a, int b);\n\nstatic int\nadd(int a,\n    int b)\n{ ... }\n"), "{}", out);
+    }
+
+    #[test]
+    fn cpp_outline_nests_classes_and_namespaces() {
+        let src = "namespace geo {\n\ntemplate <typename T>\nclass Box {\npublic:\n    T get() const { return v_; }\n    void set(T v);\n};\n\ntemplate <typename T>\nvoid Box<T>::set(T v) {\n    v_ = v;\n}\n\n}\n";
+        assert_eq!(symbols("cpp", src), [
+            sym(0, "namespace", "geo", 1, 15),
+            sym(1, "class", "Box", 3, 8),
+            sym(2, "function", "get", 6, 6),
+            sym(2, "function", "set", 7, 7),
+            sym(1, "function", "Box<T>::set", 10, 13),
+        ]);
+    }
+
+    #[test]
+    fn comments_attach_only_when_adjacent() {
+        let src = "// Detached.\n\n// Attached.\nfn a() {}\nfn b() {} // trailing\nfn c() {}\n";
+        assert_eq!(symbols("rust", src), [sym(0, "fn", "a", 3, 4), sym(0, "fn", "b", 5, 5), sym(0, "fn", "c", 6, 6)]);
+    }
+
+    #[test]
+    fn unknown_languages() {
+        assert!(outline("haskell", "main = pure ()").is_empty());
+        assert!(skeleton("haskell", "main = pure ()").is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const FILE: &str = "presets.toml";
+
+/// A named selection saved in `.sharkit/presets.toml`, e.g.
+///
+/// ```toml
+/// [parser]
+/// paths = ["src/parser.rs", "src/lexer.rs"]
+/// globs = ["src/parser/**"]
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Preset {
+    pub name: String,
+    pub paths: Vec<String>,
+    pub globs: Vec<String>,
+}
+
+/// One table of `presets.toml`; the table name is the preset name.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Table {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    paths: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    globs: Vec<String>,
+}
+
+/// All presets for the project; a missing file just means there are none.
+pub fn load(root: &Path) -> Result<Vec<Preset>> {
+    Ok(config::load(root, FILE, parse)?.unwrap_or_default())
+}
+
+pub fn find(root: &Path, name: &str) -> Result<Preset> {
+    let presets = load(root)?;
+    let known: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+    match presets.iter().find(|p| p.name == name) {
+        Some(preset) => Ok(preset.clone()),
+        None if known.is_empty() => bail!("no preset named `{}` (no presets saved in {})", name, config::path(root, FILE).display()),
+        None => bail!("no preset named `{}` (have: {})", name, known.join(", ")),
+    }
+}
+
+/// Adds `preset` to the project's presets, replacing one with the same name.
+pub fn save(root: &Path, preset: Preset) -> Result<()> {
+    let mut presets = load(root)?;
+    match presets.iter_mut().find(|p| p.name == preset.name) {
+        Some(existing) => *existing = preset,
+        None => presets.push(preset),
+    }
+    let path = config::path(root, FILE);
+    fs::create_dir_all(path.parent().expect("presets file has a parent"))?;
+    fs::write(&path, render(&presets)?).with_context(|| format!("writing {}", path.display()))
+}
+
+fn render(presets: &[Preset]) -> Result<String> {
+    let tables: IndexMap<&str, Table> = presets.iter()
+        .map(|p| (p.name.as_str(), Table { paths: p.paths.clone(), globs: p.globs.clone() }))
+        .collect();
+    Ok(format!("# Saved sharkit selections. Load one with `sharkit --preset <name>`.\n\n{}", toml::to_string_pretty(&tables)?))
+}
+
+fn parse(text: &str) -> Result<Vec<Preset>> {
+    let tables: IndexMap<String, Table> = toml::from_str(text)?;
+    Ok(tables.into_iter().map(|(name, t)| Preset { name, paths: t.paths, globs: t.globs }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(name: &str, paths: &[&str], globs: &[&str]) -> Preset {
+        Preset { name: name.into(), paths: paths.iter().map(|s| s.to_string()).collect(), globs: globs.iter().map(|s| s.to_string()).collect() }
+    }
+
+    #[test]
+    fn render_round_trips() {
+        let presets = vec![
+            preset("parser", &["src/parser.rs", "src/lexer.rs"], &["src/parser/**"]),
+            preset("odd name \"quoted\"", &["dir with space/a\\b.rs"], &[]),
+        ];
+        let text = render(&presets).unwrap();
+        let back = parse(&text).unwrap();
+        assert_eq!(back.len(), 2);
+        for (a, b) in presets.iter().zip(&back) {
+            assert_eq!((&a.name, &a.paths, &a.globs), (&b.name, &b.paths, &b.globs));
+        }
+    }
+
+    #[test]
+    fn parses_hand_written_toml() {
+        let text = "# mine\n[api] # trailing comment\npaths = ['src/api.rs', \"src/\\u00e9.rs\"]\n\n[\"web ui\"]\nglobs = [\n  \"web/**\",  # the frontend\n]\n";
+        let presets = parse(text).unwrap();
+        assert_eq!(presets[0].name, "api");
+        assert_eq!(presets[0].paths, ["src/api.rs", "src/é.rs"]);
+        assert_eq!(presets[1].name, "web ui");
+        assert_eq!(presets[1].globs, ["web/**"]);
+    }
+
+    #[test]
+    fn rejects_bad_input_with_line_numbers() {
+        let err = parse("[a]\npaths = [\"x\"]\nfiles = [\"y\"]\n").unwrap_err().to_string();
+        assert!(err.contains("line 3") && err.contains("files"), "{}", err);
+        let err = parse("[a]\npaths = [\"x\"] globs = [\"y\"]\n").unwrap_err().to_string();
+        assert!(err.contains("line 2"), "{}", err);
+    }
+}
@@ -0,0 +1,176 @@
+use std::{cell::RefCell, collections::BTreeMap, ops::Range, path::Path};
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use regex_automata::meta::Regex;
+use serde::Deserialize;
+
+use crate::{config, secrets};
+
+/// Patterns rules can use by name (`pattern = "email"`), on top of the
+/// secret scanner's rules and `secrets` for all of them.
+const NAMED: &[(&str, &str)] = &[
+    ("email", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}"),
+    ("ipv4", r"\b(?:(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\b"),
+    ("uuid", r"\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b"),
+    ("url-credentials", r"[A-Za-z][A-Za-z0-9+.-]*://[^/\s:@]+:[^/\s@]+@"),
+];
+
+enum Matcher {
+    Regex(Regex),
+    /// One of the secret scanner's rules, or all of them.
+    Secrets(Option<&'static str>),
+}
+
+struct Rule {
+    name: String,
+    matcher: Matcher,
+}
+
+/// Masks spans of emitted text with `[REDACTED:<rule>]`, using the rules in
+/// `.sharkit/redact.toml`, e.g.
+///
+/// ```toml
+/// [internal-host]
+/// regex = '\bcorp-[a-z0-9]+\.internal\b'
+///
+/// [emails]
+/// pattern = "email"
+/// ```
+///
+/// The files themselves are never touched.
+pub struct Redactor {
+    rules: Vec<Rule>,
+    /// Also mask everything the secret scanner finds, under its rule names.
+    pub secrets: bool,
+    /// Redactions made so far, per file, for the closing report.
+    counts: RefCell<BTreeMap<String, usize>>,
+}
+
+impl Redactor {
+    /// The project's rules; a missing file just means there are none.
+    pub fn load(root: &Path) -> Result<Self> {
+        let rules = config::load(root, "redact.toml", parse)?.unwrap_or_default();
+        Ok(Self { rules, secrets: false, counts: RefCell::new(BTreeMap::new()) })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.secrets || !self.rules.is_empty()
+    }
+
+    /// Non-overlapping byte ranges of `text` to mask, in order, each with the
+    /// name of the rule that matched.
+    pub fn spans(&self, text: &str) -> Vec<(Range<usize>, &str)> {
+        let mut found: Vec<(Range<usize>, &str)> = Vec::new();
+        let needs_scan = self.secrets || self.rules.iter().any(|r| matches!(r.matcher, Matcher::Secrets(_)));
+        let scanned = if needs_scan { secrets::scan(text) } else { Vec::new() };
+        if self.secrets {
+            found.extend(scanned.iter().map(|f| (f.span.clone(), f.rule)));
+        }
+        for rule in &self.rules {
+            match &rule.matcher {
+                Matcher::Regex(re) => found.extend(re.find_iter(text).filter(|m| !m.is_empty()).map(|m| (m.range(), rule.name.as_str()))),
+                Matcher::Secrets(which) => found.extend(scanned.iter()
+                    .filter(|f| which.is_none_or(|w| w == f.rule))
+                    .map(|f| (f.span.clone(), rule.name.as_str()))),
+            }
+        }
+        // Where matches overlap, the one starting first (then the longest) wins.
+        found.sort_by_key(|(r, _)| (r.start, std::cmp::Reverse(r.end)));
+        let mut end = 0;
+        found.retain(|(r, _)| {
+            let keep = r.start >= end;
+            if keep { end = r.end; }
+            keep
+        });
+        found
+    }
+
+    /// `text` with every span masked. Redactions are counted under `file`.
+    pub fn apply(&self, file: &str, text: String) -> String {
+        if !self.is_active() { return text; }
+        let spans = self.spans(&text);
+        if spans.is_empty() { return text; }
+        let mut out = String::with_capacity(text.len());
+        let mut pos = 0;
+        for (range, rule) in &spans {
+            out.push_str(&text[pos..range.start]);
+            out.push_str(&format!("[REDACTED:{}]", rule));
+            pos = range.end;
+        }
+        out.push_str(&text[pos..]);
+        *self.counts.borrow_mut().entry(file.to_string()).or_default() += spans.len();
+        out
+    }
+
+    /// `(file, redactions)` for every file that had something masked.
+    pub fn counts(&self) -> Vec<(String, usize)> {
+        self.counts.borrow().iter().map(|(f, n)| (f.clone(), *n)).collect()
+    }
+}
+
+/// One table of `redact.toml`; the table name is the rule name.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Table {
+    regex: Option<String>,
+    pattern: Option<String>,
+}
+
+fn parse(text: &str) -> Result<Vec<Rule>> {
+    let tables: IndexMap<String, Table> = toml::from_str(text)?;
+    tables.into_iter().map(|(name, table)| {
+        let matcher = match (table.regex, table.pattern) {
+            (Some(regex), None) => Matcher::Regex(Regex::new(&regex).map_err(|e| {
+                // The syntax error underneath says what's actually wrong.
+                let detail = std::error::Error::source(&e).map_or(e.to_string(), |s| s.to_string());
+                anyhow::anyhow!("bad regex for `{}`: {}", name, detail)
+            })?),
+            (None, Some(pattern)) => named(&pattern).with_context(|| format!("rule `{}`: unknown pattern `{}` (known: {})", name, pattern, known_names()))?,
+            (Some(_), Some(_)) => bail!("rule `{}` takes a regex or a pattern, not both", name),
+            (None, None) => bail!("rule `{}` needs a regex or a pattern", name),
+        };
+        Ok(Rule { name, matcher })
+    }).collect()
+}
+
+fn named(name: &str) -> Option<Matcher> {
+    if name == "secrets" { return Some(Matcher::Secrets(None)); }
+    if let Some(rule) = secrets::RULES.iter().find(|r| **r == name) { return Some(Matcher::Secrets(Some(rule))); }
+    let (_, pattern) = NAMED.iter().find(|(n, _)| *n == name)?;
+    Some(Matcher::Regex(Regex::new(pattern).expect("built-in patterns compile")))
+}
+
+fn known_names() -> String {
+    let mut names = vec!["secrets"];
+    names.extend(secrets::RULES);
+    names.extend(NAMED.iter().map(|(n, _)| *n));
+    names.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(toml: &str) -> Redactor {
+        Redactor { rules: parse(toml).unwrap(), secrets: false, counts: RefCell::new(BTreeMap::new()) }
+    }
+
+    #[test]
+    fn regex_and_named_rules() {
+        let r = redactor("[host]\nregex = '\\bcorp-[a-z0-9]+\\.internal\\b'\n\n[mail]\npattern = \"email\"\n");
+        let out = r.apply("a.txt", "ssh corp-db1.internal as ops@example.com\n".into());
+        assert_eq!(out, "ssh [REDACTED:host] as [REDACTED:mail]\n");
+        assert_eq!(r.counts(), [("a.txt".to_string(), 2)]);
+    }
+
+    #[test]
+    fn rule_errors() {
+        let err = |text: &str| parse(text).err().map(|e| e.to_string()).unwrap_or_default();
+        assert!(err("[x]\nregex = 'a'\npattern = \"email\"\n").contains("not both"));
+        assert!(err("[x]\n").contains("needs a regex or a pattern"));
+        assert!(err("[x]\npattern = \"phone\"\n").contains("unknown pattern `phone`"));
+        assert!(err("[x]\nregex = '('\n").contains("bad regex for `x`"));
+        assert!(err("[x]\nregexp = 'a'\n").contains("line 2"));
+    }
+}
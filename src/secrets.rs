@@ -0,0 +1,264 @@
+use std::{collections::{HashMap, HashSet}, fs, ops::Range, path::Path};
+
+use crate::content::{self, Kind};
+
+/// Files bigger than this only get their name checked while browsing; the
+/// full scan before emitting still reads all of them.
+pub const BROWSE_SCAN_LIMIT: u64 = 1 << 20;
+
+/// Every rule a `Finding` can come from.
+pub const RULES: &[&str] = &[
+    "private-key", "aws-access-key", "jwt", "github-token", "slack-token", "google-api-key", "stripe-key", "api-key", "secret-assignment",
+];
+
+/// Something in a file that looks like a credential. `span` is a byte range
+/// into the scanned text.
+pub struct Finding {
+    pub rule: &'static str,
+    pub span: Range<usize>,
+}
+
+/// Runs every detector over `text`. Findings may overlap.
+pub fn scan(text: &str) -> Vec<Finding> {
+    let mut found = pem_blocks(text);
+    found.extend(tokens(text));
+    found.extend(assignments(text));
+    found.sort_by_key(|f| f.span.start);
+    found
+}
+
+/// Human-readable reasons to worry about `path`: its name, and what's in it
+/// (unless it's binary or, with `limit`, larger than that many bytes).
+pub fn check_file(path: &Path, limit: Option<u64>) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if let Some(what) = path.file_name().and_then(|n| risky_name(&n.to_string_lossy())) {
+        reasons.push(format!("file name looks like {}", what));
+    }
+    let too_big = limit.is_some_and(|l| fs::metadata(path).is_ok_and(|m| m.len() > l));
+    if too_big || matches!(content::sniff_file(path), Ok(Kind::Binary) | Err(_)) {
+        return reasons;
+    }
+    let Ok(text) = content::read_text(path) else { return reasons };
+    let mut seen = HashSet::new();
+    for f in scan(&text) {
+        let line = text[..f.span.start].matches('\n').count() + 1;
+        if seen.insert((line, f.rule)) {
+            reasons.push(format!("line {}: {}", line, f.rule));
+        }
+    }
+    reasons
+}
+
+/// Names that almost always hold credentials, whatever is inside.
+fn risky_name(name: &str) -> Option<&'static str> {
+    let lower = name.to_ascii_lowercase();
+    let ext = lower.rsplit_once('.').map(|(_, e)| e);
+    match lower.as_str() {
+        ".env" => Some("an env file"),
+        n if n.starts_with(".env.") && !matches!(ext, Some("example" | "sample" | "template" | "dist")) => Some("an env file"),
+        "id_rsa" | "id_dsa" | "id_ecdsa" | "id_ed25519" => Some("an SSH private key"),
+        "credentials" | "credentials.json" | "credentials.yml" | "credentials.yaml" => Some("a credentials file"),
+        ".netrc" | ".pgpass" | ".npmrc" | ".pypirc" | ".htpasswd" => Some("a credentials file"),
+        _ if matches!(ext, Some("pem" | "key" | "p12" | "pfx" | "jks" | "keystore")) => Some("a key or certificate store"),
+        _ => None,
+    }
+}
+
+/// `-----BEGIN ... PRIVATE KEY-----` through the matching END line.
+fn pem_blocks(text: &str) -> Vec<Finding> {
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(at) = text[from..].find("-----BEGIN ") {
+        let start = from + at;
+        let Some(label_len) = text[start + 11..].find("-----") else { break };
+        let label = &text[start + 11..start + 11 + label_len];
+        let header_end = start + 11 + label_len + 5;
+        from = header_end;
+        if !label.ends_with("PRIVATE KEY") || !label.chars().all(|c| c.is_ascii_uppercase() || c == ' ') { continue; }
+        let end = match text[header_end..].find("-----END ") {
+            Some(i) => {
+                let footer = header_end + i + 9;
+                text[footer..].find("-----").map_or(text.len(), |j| footer + j + 5)
+            }
+            None => text.len(),
+        };
+        found.push(Finding { rule: "private-key", span: start..end });
+        from = end;
+    }
+    found
+}
+
+/// Well-known token formats, matched against runs of token-ish characters.
+fn tokens(text: &str) -> Vec<Finding> {
+    let is_token_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | '+' | '=');
+    let mut found = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (is_token_char(c), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                if let Some((rule, span)) = token_rule(&text[s..i]) {
+                    found.push(Finding { rule, span: s + span.start..s + span.end });
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+fn token_rule(word: &str) -> Option<(&'static str, Range<usize>)> {
+    let alnum_run = |s: &str, allowed: &dyn Fn(char) -> bool| s.find(|c: char| !allowed(c)).unwrap_or(s.len());
+    for prefix in ["AKIA", "ASIA", "ABIA", "ACCA"] {
+        if let Some(at) = word.find(prefix) {
+            let rest = &word[at + 4..];
+            if alnum_run(rest, &|c| c.is_ascii_uppercase() || c.is_ascii_digit()) >= 16 {
+                return Some(("aws-access-key", at..at + 20));
+            }
+        }
+    }
+    let base64url = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    if let Some(at) = word.find("eyJ") {
+        let parts: Vec<&str> = word[at..].splitn(3, '.').collect();
+        if parts.len() == 3 && parts[1].starts_with("eyJ") && parts.iter().all(|p| !p.is_empty()) {
+            let sig = alnum_run(parts[2], &base64url);
+            if sig > 0 && parts[..2].iter().all(|p| p.chars().all(base64url)) {
+                return Some(("jwt", at..at + parts[0].len() + parts[1].len() + 2 + sig));
+            }
+        }
+    }
+    for (prefix, min, rule) in [
+        ("ghp_", 36, "github-token"), ("gho_", 36, "github-token"), ("ghu_", 36, "github-token"),
+        ("ghs_", 36, "github-token"), ("ghr_", 36, "github-token"), ("github_pat_", 22, "github-token"),
+        ("xoxb-", 10, "slack-token"), ("xoxp-", 10, "slack-token"), ("xoxa-", 10, "slack-token"),
+        ("AIza", 35, "google-api-key"), ("sk_live_", 16, "stripe-key"), ("rk_live_", 16, "stripe-key"),
+        ("sk-", 32, "api-key"),
+    ] {
+        if let Some(at) = word.find(prefix) {
+            let len = alnum_run(&word[at + prefix.len()..], &base64url);
+            if len >= min { return Some((rule, at..at + prefix.len() + len)); }
+        }
+    }
+    None
+}
+
+/// `password = "..."`, `API_TOKEN: ...` and friends, when the value looks
+/// random rather than like code or a placeholder.
+fn assignments(text: &str) -> Vec<Finding> {
+    const KEYS: [&str; 10] = ["secret", "password", "passwd", "token", "api_key", "apikey", "api-key", "access_key", "private_key", "credential"];
+    let mut found = Vec::new();
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let offset = line_start;
+        line_start += line.len();
+        let lower = line.to_ascii_lowercase();
+        // The keyword has to end the word, so `tokenizer` isn't a token.
+        let ends_word = |at: usize, k: &str| !lower[at + k.len()..].starts_with(|c: char| c.is_ascii_alphabetic());
+        let Some(key_at) = KEYS.iter().filter_map(|k| lower.match_indices(k).map(|(at, _)| at).find(|&at| ends_word(at, k))).min() else { continue };
+        // Past the rest of the key, then `=`, `:`, `:=` or `=>`, in any quoting.
+        let after_key = key_at + line[key_at..].find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))).unwrap_or(line.len() - key_at);
+        let rest = line[after_key..].trim_start_matches(['"', '\'', ' ', '\t']);
+        let Some(rest) = rest.strip_prefix(":=").or_else(|| rest.strip_prefix("=>")).or_else(|| rest.strip_prefix(['=', ':'])) else { continue };
+        let trimmed = rest.trim_start();
+        let quote = trimmed.chars().next().filter(|c| matches!(c, '"' | '\'' | '`'));
+        let value_start = line.len() - trimmed.len() + quote.map_or(0, |q| q.len_utf8());
+        let value = &line[value_start..];
+        let value_len = match quote {
+            Some(q) => value.find(q).unwrap_or(value.trim_end().len()),
+            None => value.find(|c: char| c.is_whitespace() || matches!(c, ',' | ';')).unwrap_or(value.len()),
+        };
+        let value = &value[..value_len];
+        if looks_secret(value) {
+            found.push(Finding { rule: "secret-assignment", span: offset + value_start..offset + value_start + value_len });
+        }
+    }
+    found
+}
+
+fn looks_secret(value: &str) -> bool {
+    if value.len() < 8 || interpolated(value) || value.chars().any(|c| matches!(c, '(' | '<' | '>' | '[' | ' ')) {
+        return false;
+    }
+    let lower = value.to_ascii_lowercase();
+    if ["example", "changeme", "your", "xxxx", "****", "placeholder", "dummy", "redacted"].iter().any(|p| lower.contains(p)) {
+        return false;
+    }
+    // Identifiers and paths like `self.token` or `config::API_KEY` are code.
+    if value.chars().all(|c| c.is_alphabetic() || matches!(c, '_' | '.' | ':' | '/' | '-')) || value.contains("::") {
+        return false;
+    }
+    entropy(value) >= 3.0
+}
+
+/// `${VAR}`, `$VAR`, `%(name)s`, `{name}`, `{{ x }}` and `#{x}` are filled in
+/// from somewhere else; a `$` or `%` on its own is just part of the value.
+fn interpolated(value: &str) -> bool {
+    let ident = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    ["${", "%(", "{{", "#{"].iter().any(|p| value.contains(p))
+        || value.strip_prefix('$').is_some_and(ident)
+        || value.strip_prefix('{').and_then(|v| v.strip_suffix('}')).is_some_and(ident)
+}
+
+/// Shannon entropy in bits per character.
+fn entropy(s: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in s.chars() { *counts.entry(c).or_default() += 1; }
+    let n = s.chars().count() as f64;
+    counts.values().map(|&c| { let p = c as f64 / n; -p * p.log2() }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rules that fire on the concatenation of `parts`. Fixtures are split so
+    /// this file doesn't trip its own scanner.
+    fn rules(parts: &[&str]) -> Vec<&'static str> {
+        scan(&parts.concat()).iter().map(|f| f.rule).collect()
+    }
+
+    #[test]
+    fn each_rule_fires() {
+        assert_eq!(rules(&["-----BEGIN RSA PRIVATE", " KEY-----\nMIIEow\n-----END RSA PRIVATE KEY-----\n"]), ["private-key"]);
+        assert_eq!(rules(&["id = AKIA", "IOSFODNN7QWERTY1"]), ["aws-access-key"]);
+        assert_eq!(rules(&["auth: eyJhbGciOiJIUzI1NiJ9", ".eyJzdWIiOiIxIn0", ".c2lnbmF0dXJl"]), ["jwt"]);
+        assert_eq!(rules(&["ghp_", &"a1B2".repeat(9)]), ["github-token"]);
+        assert_eq!(rules(&["xoxb-", "1234567890-abcdef"]), ["slack-token"]);
+        assert_eq!(rules(&["AIza", &"x9".repeat(18)]), ["google-api-key"]);
+        assert_eq!(rules(&["sk_live_", &"Ab3".repeat(8)]), ["stripe-key"]);
+        assert_eq!(rules(&["sk-", &"proj9".repeat(8)]), ["api-key"]);
+        assert_eq!(rules(&["pass", "word = \"hX9$kq2Lz\""]), ["secret-assignment"]);
+        assert_eq!(rules(&["DB_PASS", "WORD: 'p%4Kq9z!xW'"]), ["secret-assignment"]);
+        assert_eq!(rules(&["api_", "key => `Zq8{w2Lm0Pv`"]), ["secret-assignment"]);
+    }
+
+    #[test]
+    fn spans_cover_the_value() {
+        let text = ["let tok", "en = \"hX9$kq2Lz\";"].concat();
+        let found = scan(&text);
+        assert_eq!(&text[found[0].span.clone()], "hX9$kq2Lz");
+    }
+
+    #[test]
+    fn tokenizer_is_not_a_token() {
+        assert!(rules(&["tokeni", "zer = \"cl100k_Q9x7Lz\""]).is_empty());
+        assert!(rules(&["secret", "ary: 'hX9$kq2Lz'"]).is_empty());
+    }
+
+    #[test]
+    fn interpolation_and_placeholders_are_skipped() {
+        for value in ["${DB_PASSWORD}", "$DB_PASSWORD", "%(db_password)s", "{db_password}", "{{ .Values.pw }}", "#{ENV['PW']}", "changeme123", "<your-password>", "self.password_field", "config::API_KEY"] {
+            assert!(rules(&["pass", "word = \"", value, "\""]).is_empty(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn risky_names() {
+        assert!(risky_name(".env").is_some());
+        assert!(risky_name(".env.production").is_some());
+        assert!(risky_name(".env.example").is_none());
+        assert!(risky_name("server.pem").is_some());
+        assert!(risky_name("main.rs").is_none());
+    }
+}
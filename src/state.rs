@@ -0,0 +1,56 @@
+use std::{env, fs, path::{Path, PathBuf}};
+
+use anyhow::{Context, Result};
+
+/// Where sharkit keeps per-machine state: `$XDG_STATE_HOME/sharkit`, falling
+/// back to `~/.local/state/sharkit`.
+pub fn dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+        Some(state) => PathBuf::from(state),
+        None => PathBuf::from(env::var_os("HOME").filter(|v| !v.is_empty())?).join(".local").join("state"),
+    };
+    Some(base.join("sharkit"))
+}
+
+/// The file holding the last selection for `root`, named after a hash of its
+/// canonical path.
+fn selection_file(root: &Path) -> Option<(PathBuf, String)> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let key = root.to_string_lossy().into_owned();
+    let file = dir()?.join("selections").join(format!("{:016x}.txt", fnv1a(key.as_bytes())));
+    Some((file, key))
+}
+
+/// The paths (relative to `root`) confirmed on the last run, if any.
+pub fn load_selection(root: &Path) -> Vec<String> {
+    let Some((file, key)) = selection_file(root) else { return Vec::new() };
+    let Ok(text) = fs::read_to_string(file) else { return Vec::new() };
+    let mut lines = text.lines();
+    // The first line names the project, guarding against hash collisions.
+    if lines.next() != Some(&format!("# {}", key)) { return Vec::new(); }
+    lines.filter(|l| !l.is_empty()).map(str::to_string).collect()
+}
+
+pub fn save_selection(root: &Path, paths: &[String]) -> Result<()> {
+    let Some((file, key)) = selection_file(root) else { return Ok(()) };
+    fs::create_dir_all(file.parent().expect("selection file has a parent"))?;
+    let mut text = format!("# {}\n", key);
+    for p in paths {
+        text.push_str(p);
+        text.push('\n');
+    }
+    fs::write(&file, text).with_context(|| format!("writing {}", file.display()))
+}
+
+pub fn clear_selection(root: &Path) -> Result<()> {
+    let Some((file, _)) = selection_file(root) else { return Ok(()) };
+    match fs::remove_file(&file) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e).with_context(|| format!("removing {}", file.display())),
+        _ => Ok(()),
+    }
+}
+
+/// 64-bit FNV-1a; stable across builds, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, &b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
@@ -0,0 +1,103 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Result};
+
+use crate::content::{self, Kind};
+
+/// How tokens are counted. Both run offline.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tokenizer {
+    /// Four bytes per token. Needs only the file size, never the contents.
+    Heuristic,
+    /// Exact counts with the bundled cl100k_base ranks (GPT-4 and
+    /// GPT-3.5-turbo); a close proxy for other models.
+    Cl100k,
+}
+
+impl Tokenizer {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "heuristic" => Tokenizer::Heuristic,
+            "cl100k" => Tokenizer::Cl100k,
+            _ => bail!("unknown tokenizer `{}` (expected heuristic or cl100k)", s),
+        })
+    }
+
+    pub fn count(self, text: &str) -> usize {
+        match self {
+            Tokenizer::Heuristic => text.len().div_ceil(4),
+            Tokenizer::Cl100k => tiktoken_rs::cl100k_base_singleton().encode_ordinary(text).len(),
+        }
+    }
+
+    /// Binary files count as zero since they are never emitted.
+    pub fn count_file(self, path: &Path) -> usize {
+        if matches!(content::sniff_file(path), Ok(Kind::Binary)) {
+            return 0;
+        }
+        match self {
+            Tokenizer::Heuristic => fs::metadata(path).map_or(0, |m| (m.len() as usize).div_ceil(4)),
+            // Past a megabyte an exact count isn't worth the time it takes.
+            Tokenizer::Cl100k if fs::metadata(path).is_ok_and(|m| m.len() > 1 << 20) => Tokenizer::Heuristic.count_file(path),
+            Tokenizer::Cl100k => fs::read(path).map_or(0, |b| self.count(&String::from_utf8_lossy(&b))),
+        }
+    }
+}
+
+/// Parses budgets such as `8000`, `100k` or `1.5m`.
+pub fn parse_budget(s: &str) -> Result<usize> {
+    let lower = s.trim().to_ascii_lowercase();
+    let (num, mult) = match lower.strip_suffix('k') {
+        Some(n) => (n, 1_000.0),
+        None => match lower.strip_suffix('m') {
+            Some(n) => (n, 1_000_000.0),
+            None => (lower.as_str(), 1.0),
+        },
+    };
+    match num.parse::<f64>() {
+        Ok(n) if n > 0.0 => Ok((n * mult) as usize),
+        _ => bail!("invalid token budget `{}` (try 8000, 100k or 1.5m)", s),
+    }
+}
+
+/// Compact rendering for the list: `950`, `12.3k`, `1.2M`.
+pub fn format(n: usize) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=999_999 => format!("{:.1}k", n as f64 / 1_000.0),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cl100k_known_counts() {
+        let cases = [
+            ("", 0),
+            ("hello world", 2),
+            ("Hello, world!", 4),
+            ("tiktoken is great!", 6),
+        ];
+        for (text, want) in cases {
+            assert_eq!(Tokenizer::Cl100k.count(text), want, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn heuristic_is_bytes_over_four() {
+        assert_eq!(Tokenizer::Heuristic.count("hello world"), 3);
+        assert_eq!(Tokenizer::Heuristic.count(""), 0);
+    }
+
+    #[test]
+    fn budgets() {
+        assert_eq!(parse_budget("8000").unwrap(), 8000);
+        assert_eq!(parse_budget("100k").unwrap(), 100_000);
+        assert_eq!(parse_budget("1.5M").unwrap(), 1_500_000);
+        assert!(parse_budget("0").is_err());
+        assert!(parse_budget("lots").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use std::{collections::HashSet, fmt::Write, path::{Path, PathBuf}};
+
+use crate::listing::Entry;
+
+/// Limits that keep the tree header compact in large repositories.
+#[derive(Clone, Copy)]
+pub struct TreeLimits {
+    pub depth: usize,
+    pub entries: usize,
+}
+
+impl Default for TreeLimits {
+    fn default() -> Self {
+        Self { depth: 3, entries: 200 }
+    }
+}
+
+/// A `tree`-style outline of the project, with `files`, the ones the bundle
+/// holds, marked `*`. Ignored entries are left out, and so is anything past
+/// the depth or entry limits, except that included files and their
+/// directories always show.
+pub fn render(items: &[Entry], files: &[PathBuf], root_name: &str, limits: TreeLimits) -> String {
+    let files: HashSet<&Path> = files.iter().map(PathBuf::as_path).collect();
+    let mut included = vec![false; items.len()];
+    for i in (0..items.len()).rev() {
+        if !items[i].is_dir && files.contains(items[i].path.as_path()) { included[i] = true; }
+        if included[i] {
+            if let Some(p) = items[i].parent { included[p] = true; }
+        }
+    }
+
+    let mut shown = vec![false; items.len()];
+    let mut count = 0;
+    let mut omitted = 0;
+    for (i, e) in items.iter().enumerate() {
+        let parent_shown = e.parent.is_none_or(|p| shown[p]);
+        let wanted = included[i] || (parent_shown && !e.ignored && e.depth < limits.depth && count < limits.entries);
+        if wanted {
+            shown[i] = true;
+            count += 1;
+        } else if parent_shown && !e.ignored {
+            omitted += 1;
+        }
+    }
+
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); items.len()];
+    let mut top = Vec::new();
+    for (i, e) in items.iter().enumerate().filter(|&(i, _)| shown[i]) {
+        match e.parent {
+            Some(p) => children[p].push(i),
+            None => top.push(i),
+        }
+    }
+
+    let mut out = format!("{}/\n", root_name);
+    write_level(&mut out, items, &children, &top, "", &included);
+    if omitted > 0 {
+        let _ = writeln!(out, "… {} more entries not shown", omitted);
+    }
+    out
+}
+
+fn write_level(out: &mut String, items: &[Entry], children: &[Vec<usize>], level: &[usize], prefix: &str, included: &[bool]) {
+    for (n, &i) in level.iter().enumerate() {
+        let last = n + 1 == level.len();
+        let e = &items[i];
+        let name = if e.is_dir { format!("{}/", e.name) } else { e.name.clone() };
+        let mark = if included[i] && !e.is_dir { " *" } else { "" };
+        let _ = writeln!(out, "{}{}{}{}", prefix, if last { "└── " } else { "├── " }, name, mark);
+        if e.is_dir {
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            write_level(out, items, children, &children[i], &prefix, included);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::{app::{App, Focus, Mark, Prompt}, content::{self, Kind}, listing::Part, tokens};
+
+pub fn draw(ui: &mut Frame, app: &App, list_state: &mut ListState) {
+    let main_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(10)].as_ref())
+        .split(ui.size());
+
+    let content_area = if app.show_preview {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+            .split(main_chunks[0])
+            .to_vec()
+    } else {
+        vec![main_chunks[0]]
+    };
+
+    let list_area = if app.filtering || !app.query.is_empty() {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+            .split(content_area[0]);
+        let cursor = if app.filtering { "█" } else { "" };
+        let bar = Paragraph::new(format!("/{}{}", app.query, cursor))
+            .block(Block::default().title(format!("Filter ({} matches)", app.visible.len())).borders(Borders::ALL));
+        ui.render_widget(bar, split[0]);
+        split[1]
+    } else {
+        content_area[0]
+    };
+
+    app.list_height.set(list_area.height.saturating_sub(2) as usize);
+    let items: Vec<ListItem> = app.visible.iter().map(|&i| {
+        let e = &app.items[i];
+        let mark = match app.mark(i) { Mark::All => "✓", Mark::Partial => "~", Mark::Skeleton => "s", Mark::None => " " };
+        let style = if e.hidden || e.ignored {
+            Style::default().fg(Color::Gray).add_modifier(Modifier::DIM)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let count = e.tokens.map(|t| Span::styled(format!("  {}", tokens::format(t)), Style::default().fg(Color::DarkGray)));
+        let part = match e.part().filter(|_| e.selected) {
+            Some(Part::Ranges(ranges)) => Some(ranges.iter().map(|&r| content::range_label(r)).collect::<Vec<_>>().join(", ")),
+            Some(Part::Skeleton) => Some("skeleton".to_string()),
+            None => None,
+        };
+        let ranges = part.map(|p| Span::styled(format!("  {}", p), Style::default().fg(Color::DarkGray).add_modifier(Modifier::DIM)));
+        let warning = e.secrets.filter(|&n| n > 0).map(|_| Span::styled("  ⚠ secrets?", Style::default().fg(Color::Red)));
+        let change = match e.git.map(|c| c.marker) {
+            Some(m) => Span::styled(m.to_string(), Style::default().fg(match m {
+                'A' => Color::Green,
+                '?' => Color::Magenta,
+                '•' => Color::DarkGray,
+                _ => Color::Yellow,
+            })),
+            None => Span::raw(" "),
+        };
+        if let Some(positions) = app.highlights.get(&i) {
+            let matched = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+            let mut spans = vec![Span::raw(format!(" [{}]", mark)), change, Span::raw(" ")];
+            spans.extend(e.rel.to_string_lossy().chars().enumerate().map(|(ci, c)| {
+                Span::styled(c.to_string(), if positions.contains(&ci) { matched } else { style })
+            }));
+            spans.extend(ranges);
+            spans.extend(count);
+            spans.extend(warning);
+            return ListItem::new(Line::from(spans)).style(style);
+        }
+        let indent = "  ".repeat(e.depth);
+        let line = if e.is_dir {
+            format!(" {}{} {}/", indent, if e.expanded { "▾" } else { "▸" }, e.name)
+        } else {
+            format!(" {}  {}", indent, e.name)
+        };
+        let mut spans = vec![Span::raw(format!(" [{}]", mark)), change, Span::raw(line)];
+        spans.extend(ranges);
+        spans.extend(count);
+        spans.extend(warning);
+        ListItem::new(Line::from(spans)).style(style)
+    }).collect();
+
+    let mut list_block = Block::default().title("sharkit").borders(Borders::ALL);
+    if let Some(status) = &app.status {
+        list_block = list_block.title_bottom(Line::styled(format!(" {} ", status), Style::default().fg(Color::Yellow)));
+    }
+    let list = List::new(items)
+        .block(list_block)
+        .highlight_style(Style::default().bg(Color::Cyan).fg(Color::Black))
+        .highlight_symbol("› ");
+
+    ui.render_stateful_widget(list, list_area, list_state);
+
+    if app.show_preview && content_area.len() > 1 {
+        let height = content_area[1].height.saturating_sub(2) as usize;
+        app.preview_height.set(height);
+        let mut preview_title = match app.current_index() {
+            Some(idx) => format!("Preview: {}", app.items[idx].name),
+            None => "Preview".to_string(),
+        };
+        match app.preview_kind() {
+            Some(Kind::Binary) => preview_title.push_str(" (binary)"),
+            Some(Kind::LossyText) => preview_title.push_str(" (lossy UTF-8)"),
+            _ => {}
+        }
+        if app.outline_view {
+            preview_title = preview_title.replacen("Preview", "Outline", 1);
+            if let Some(symbols) = &app.outline { preview_title.push_str(&format!(" ({} symbols)", symbols.len())); }
+        } else if !app.preview_lines.is_empty() {
+            let total = if app.preview_fully_loaded() { app.preview_lines.len().to_string() } else { "…".to_string() };
+            let at = if app.focus == Focus::Preview { app.preview_cursor } else { app.preview_scroll };
+            let column = if app.preview_column > 0 { format!(" · col {}", app.preview_column + 1) } else { String::new() };
+            preview_title.push_str(&format!(" [{}/{}{}]", at + 1, total, column));
+        }
+        let text = if app.outline_view {
+            outline_text(app, height)
+        } else if app.preview_lines.is_empty() {
+            Text::raw(app.preview_content.as_str())
+        } else {
+            let end = (app.preview_scroll + height).min(app.preview_lines.len());
+            let start = app.preview_scroll.min(end);
+            let picked = app.current_index().map_or(&[][..], |i| app.items[i].ranges.as_slice());
+            let visual = app.visual_span();
+            Text::from((start..end).map(|n| {
+                let mut line = skip_columns(app.preview_lines[n].clone(), app.preview_column);
+                if picked.iter().any(|&(s, e)| (s..=e).contains(&(n + 1))) {
+                    if let Some(gutter) = line.spans.first_mut() { gutter.style = Style::default().fg(Color::Green).add_modifier(Modifier::BOLD); }
+                }
+                if visual.is_some_and(|(s, e)| (s..=e).contains(&n)) {
+                    line.style = line.style.bg(Color::Blue);
+                } else if app.focus == Focus::Preview && n == app.preview_cursor {
+                    line.style = line.style.bg(Color::DarkGray);
+                }
+                line
+            }).collect::<Vec<_>>())
+        };
+        let border = if app.focus == Focus::Preview { Style::default().fg(Color::Cyan) } else { Style::default() };
+        let mut preview = Paragraph::new(text)
+            .block(Block::default().title(preview_title).borders(Borders::ALL).border_style(border));
+        // Scrolling and the cursor count logical lines, so file lines are
+        // clipped at the edge rather than wrapped onto rows they don't own;
+        // h/l bring the rest into view.
+        if !app.outline_view && app.preview_lines.is_empty() { preview = preview.wrap(Wrap { trim: false }); }
+
+        ui.render_widget(preview, content_area[1]);
+    }
+
+    let help_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+        .split(main_chunks[1]);
+
+    let navigation_help = Paragraph::new("Navigation:\n[↑/↓ or j/k] move cursor  [←/→ or h/l] collapse/expand\n[space] toggle selection (whole directory on folders)\n[/] fuzzy filter (esc clears)  [tab] focus preview: j/k, PgUp/PgDn, g/G move, h/l sideways\n[v] in preview: mark lines, [y] add them as a range  [d] whole file again\n[O] outline view: [space] picks the symbol under the cursor\n[K] skeleton: declarations only, function bodies left out\n[enter] confirm paths  [q/esc] quit")
+        .block(Block::default().title("Controls").borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    ui.render_widget(navigation_help, help_chunks[0]);
+
+    let total = app.selected_tokens();
+    let meter = match app.budget {
+        Some(budget) => format!("{} selected · ~{} / {} tokens", app.selected_count(), tokens::format(total), tokens::format(budget)),
+        None => format!("{} selected · ~{} tokens", app.selected_count(), tokens::format(total)),
+    };
+    let meter_style = if app.over_budget() { Style::default().fg(Color::Red).add_modifier(Modifier::BOLD) } else { Style::default() };
+    let selection_help = Paragraph::new(vec![
+        Line::raw("Selection:"),
+        Line::raw("[a] all (no hidden/ignored)  [A] all incl. hidden"),
+        Line::raw("[i] only ignored  [*] invert  [n] none  [x] none + forget last run"),
+        Line::raw(format!("[p] toggle preview  [t] tree header: {}", if app.include_tree { "on" } else { "off" })),
+        Line::raw("[m] modified  [S] staged  [u] untracked  [M] all changed"),
+        Line::raw("[s] save preset  [o] open preset"),
+        Line::raw("[b] confirm as bundle  [c] copy bundle"),
+        Line::styled(meter, meter_style),
+    ])
+        .block(Block::default().title("Actions").borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    ui.render_widget(selection_help, help_chunks[1]);
+
+    if let Some(prompt) = &app.prompt {
+        draw_prompt(ui, prompt);
+    }
+}
+
+/// The previewed file's symbols, indented by nesting, with a mark on the
+/// ones picked and the cursor kept on screen.
+fn outline_text(app: &App, height: usize) -> Text<'static> {
+    let Some(symbols) = app.outline.as_ref().filter(|s| !s.is_empty()) else {
+        return Text::raw("<no outline for this file>");
+    };
+    let first = app.outline_cursor.saturating_sub(height.saturating_sub(1));
+    Text::from(symbols.iter().enumerate().skip(first).take(height).map(|(i, s)| {
+        let mark = if app.symbol_picked(s) { "✓" } else { " " };
+        let mut line = Line::from(vec![
+            Span::raw(format!("[{}] {}", mark, "  ".repeat(s.depth))),
+            Span::styled(format!("{} ", s.kind), Style::default().fg(Color::Magenta)),
+            Span::raw(s.name.clone()),
+            Span::styled(format!("  {}", content::range_label((s.start, s.end))), Style::default().fg(Color::DarkGray)),
+        ]);
+        if app.focus == Focus::Preview && i == app.outline_cursor { line.style = line.style.bg(Color::DarkGray); }
+        line
+    }).collect::<Vec<_>>())
+}
+
+fn draw_prompt(ui: &mut Frame, prompt: &Prompt) {
+    let area = ui.size();
+    let (title, body, height) = match prompt {
+        Prompt::SavePreset(name) => ("Save preset as (enter to save, esc to cancel)", vec![Line::raw(format!("{}█", name))], 3),
+        Prompt::LoadPreset { presets, cursor } => {
+            let lines = presets.iter().enumerate().map(|(i, p)| {
+                let line = format!("{} ({} paths, {} globs)", p.name, p.paths.len(), p.globs.len());
+                if i == *cursor { Line::styled(format!("› {}", line), Style::default().bg(Color::Cyan).fg(Color::Black)) } else { Line::raw(format!("  {}", line)) }
+            }).collect();
+            ("Load preset (enter to load, esc to cancel)", lines, presets.len() as u16 + 2)
+        }
+        Prompt::Secrets { files, .. } => {
+            let mut lines = Vec::new();
+            for (rel, reasons) in files {
+                lines.push(Line::styled(format!("⚠ {}", rel), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+                lines.extend(reasons.iter().take(3).map(|r| Line::raw(format!("    {}", r))));
+                if reasons.len() > 3 { lines.push(Line::raw(format!("    … and {} more", reasons.len() - 3))); }
+            }
+            lines.push(Line::raw(""));
+            lines.push(Line::styled("[r] redact and emit  [y] emit as is  [esc] back", Style::default().fg(Color::Yellow)));
+            let height = lines.len() as u16 + 2;
+            ("These files may hold secrets", lines, height)
+        }
+    };
+    let width = area.width.saturating_sub(4).min(if matches!(prompt, Prompt::Secrets { .. }) { 90 } else { 60 });
+    let height = height.min(area.height.saturating_sub(2));
+    let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+    ui.render_widget(Clear, popup);
+    ui.render_widget(Paragraph::new(body).block(Block::default().title(title).borders(Borders::ALL)), popup);
+}
+
+/// `line` with the first `cols` characters after its gutter dropped.
+fn skip_columns(mut line: Line<'static>, mut cols: usize) -> Line<'static> {
+    for span in line.spans.iter_mut().skip(1) {
+        if cols == 0 { break; }
+        let len = span.content.chars().count();
+        span.content = span.content.chars().skip(cols).collect::<String>().into();
+        cols = cols.saturating_sub(len);
+    }
+    line
+}